@@ -1,4 +1,7 @@
-use crate::{ChildError, Espeak, EspeakError, EspeakSpeech};
+use crate::{
+    Backend, ChildError, Espeak, EspeakError, EspeakSpeech, Preset, Settings, SsmlError, Stderr,
+    Utterance, VoiceInfo,
+};
 #[cfg(target_os = "windows")]
 use crate::{CScriptVoice, CScriptVoiceError, CScriptVoiceSpeech};
 #[cfg(target_os = "macos")]
@@ -8,6 +11,7 @@ use std::path::Path;
 
 /// A [`Voice`](trait.Voice.html) that works with any of
 /// the built-in techniques (currently only espeak).
+#[derive(Clone)]
 pub enum AnyVoice {
     #[cfg(target_os = "windows")]
     CScript(CScriptVoice),
@@ -38,6 +42,12 @@ pub enum AnyError {
     Say(SayError),
     #[fail(display = "speech synthesizer communication error: {}", _0)]
     Child(ChildError),
+    #[fail(display = "invalid SSML: {}", _0)]
+    InvalidSsml(SsmlError),
+    #[fail(display = "no voice found for language: {}", _0)]
+    NoVoiceForLanguage(String),
+    #[fail(display = "backend not available on this system: {:?}", _0)]
+    UnavailableBackend(Backend),
 }
 
 #[cfg(target_os = "windows")]
@@ -66,6 +76,12 @@ impl From<ChildError> for AnyError {
     }
 }
 
+impl From<SsmlError> for AnyError {
+    fn from(error: SsmlError) -> Self {
+        AnyError::InvalidSsml(error)
+    }
+}
+
 #[cfg(target_os = "windows")]
 impl From<CScriptVoice> for AnyVoice {
     fn from(cscript: CScriptVoice) -> Self {
@@ -86,35 +102,174 @@ impl From<Say> for AnyVoice {
     }
 }
 
+impl AnyVoice {
+    /// Lists the voices of all installed backends, `espeak` first.
+    /// Backends that are missing or fail to list their voices are
+    /// skipped.
+    pub fn voices() -> Vec<VoiceInfo> {
+        let mut voices = Vec::new();
+        if let Ok(espeak) = Espeak::new() {
+            voices.extend(espeak.voices().unwrap_or_default());
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            if let Ok(say) = Say::new() {
+                voices.extend(say.voices().unwrap_or_default());
+            }
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            if let Ok(cscript) = CScriptVoice::new() {
+                voices.extend(cscript.voices().unwrap_or_default());
+            }
+        }
+
+        voices
+    }
+
+    /// Creates a voice of the backend of the given voice, speaking
+    /// with it.
+    pub fn for_voice(info: &VoiceInfo) -> Result<AnyVoice, AnyError> {
+        match info.backend {
+            Backend::Espeak => Ok(Espeak::new()?.with_voice(&info.id, None)?.into()),
+            #[cfg(target_os = "macos")]
+            Backend::Say => Ok(Say::new()?.with_voice(&info.id)?.into()),
+            #[cfg(target_os = "windows")]
+            Backend::Sapi => Ok(CScriptVoice::new()?.with_voice(&info.id)?.into()),
+            #[allow(unreachable_patterns)]
+            backend => Err(AnyError::UnavailableBackend(backend)),
+        }
+    }
+
+    /// Creates a voice for the first of the [`voices`](#method.voices)
+    /// that speaks the language with the given tag, like `de` or
+    /// `en-GB`.
+    pub fn for_language(tag: &str) -> Result<AnyVoice, AnyError> {
+        AnyVoice::voices()
+            .iter()
+            .find(|voice| voice.speaks(tag))
+            .ok_or_else(|| AnyError::NoVoiceForLanguage(tag.to_string()))
+            .and_then(AnyVoice::for_voice)
+    }
+
+    /// Speaks with the given preset, using the options of the
+    /// backend where it has them, or prosody otherwise.
+    pub fn with_preset(self, preset: Preset) -> AnyVoice {
+        match self {
+            #[cfg(target_os = "windows")]
+            AnyVoice::CScript(mut voice) => {
+                crate::Voice::settings_mut(&mut voice).prosody = preset.prosody().to_vec();
+                AnyVoice::CScript(voice)
+            }
+            AnyVoice::Espeak(voice) => {
+                AnyVoice::Espeak(voice.with_options(preset.espeak_options()))
+            }
+            #[cfg(target_os = "macos")]
+            AnyVoice::Say(mut voice) => {
+                crate::Voice::settings_mut(&mut voice).prosody = preset.prosody().to_vec();
+                AnyVoice::Say(voice)
+            }
+        }
+    }
+}
+
 impl crate::Voice for AnyVoice {
     type Error = AnyError;
     type Speech = AnySpeech;
 
-    fn speak<S>(&self, sentence: S) -> Result<Self::Speech, Self::Error>
+    fn settings(&self) -> &Settings {
+        match self {
+            #[cfg(target_os = "windows")]
+            AnyVoice::CScript(voice) => voice.settings(),
+            AnyVoice::Espeak(voice) => voice.settings(),
+            #[cfg(target_os = "macos")]
+            AnyVoice::Say(voice) => voice.settings(),
+        }
+    }
+
+    fn settings_mut(&mut self) -> &mut Settings {
+        match self {
+            #[cfg(target_os = "windows")]
+            AnyVoice::CScript(voice) => voice.settings_mut(),
+            AnyVoice::Espeak(voice) => voice.settings_mut(),
+            #[cfg(target_os = "macos")]
+            AnyVoice::Say(voice) => voice.settings_mut(),
+        }
+    }
+
+    fn speak_utterance(&self, utterance: &Utterance) -> Result<Self::Speech, Self::Error> {
+        match self {
+            #[cfg(target_os = "windows")]
+            AnyVoice::CScript(voice) => voice
+                .speak_utterance(utterance)
+                .map(AnySpeech::CScript)
+                .map_err(From::from),
+            AnyVoice::Espeak(voice) => voice
+                .speak_utterance(utterance)
+                .map(AnySpeech::Espeak)
+                .map_err(From::from),
+            #[cfg(target_os = "macos")]
+            AnyVoice::Say(voice) => voice
+                .speak_utterance(utterance)
+                .map(AnySpeech::Say)
+                .map_err(From::from),
+        }
+    }
+
+    fn speak_utterance_to_file<P>(
+        &self,
+        utterance: &Utterance,
+        wav_file_path: P,
+    ) -> Result<Self::Speech, Self::Error>
+    where
+        P: AsRef<Path>,
+    {
+        match self {
+            #[cfg(target_os = "windows")]
+            AnyVoice::CScript(voice) => voice
+                .speak_utterance_to_file(utterance, wav_file_path)
+                .map(AnySpeech::CScript)
+                .map_err(From::from),
+            AnyVoice::Espeak(voice) => voice
+                .speak_utterance_to_file(utterance, wav_file_path)
+                .map(AnySpeech::Espeak)
+                .map_err(From::from),
+            #[cfg(target_os = "macos")]
+            AnyVoice::Say(voice) => voice
+                .speak_utterance_to_file(utterance, wav_file_path)
+                .map(AnySpeech::Say)
+                .map_err(From::from),
+        }
+    }
+
+    // Forwarded, so that backends can pass documents on unchanged
+    fn speak_ssml<S>(&self, ssml: S) -> Result<Self::Speech, Self::Error>
     where
         S: AsRef<str>,
     {
         match self {
             #[cfg(target_os = "windows")]
             AnyVoice::CScript(voice) => voice
-                .speak(sentence)
+                .speak_ssml(ssml)
                 .map(AnySpeech::CScript)
                 .map_err(From::from),
             AnyVoice::Espeak(voice) => voice
-                .speak(sentence)
+                .speak_ssml(ssml)
                 .map(AnySpeech::Espeak)
                 .map_err(From::from),
             #[cfg(target_os = "macos")]
             AnyVoice::Say(voice) => voice
-                .speak(sentence)
+                .speak_ssml(ssml)
                 .map(AnySpeech::Say)
                 .map_err(From::from),
         }
     }
 
-    fn speak_to_file<S, P>(
+    fn speak_ssml_to_file<S, P>(
         &self,
-        sentence: S,
+        ssml: S,
         wav_file_path: P,
     ) -> Result<Self::Speech, Self::Error>
     where
@@ -124,22 +279,35 @@ impl crate::Voice for AnyVoice {
         match self {
             #[cfg(target_os = "windows")]
             AnyVoice::CScript(voice) => voice
-                .speak_to_file(sentence, wav_file_path)
+                .speak_ssml_to_file(ssml, wav_file_path)
                 .map(AnySpeech::CScript)
                 .map_err(From::from),
             AnyVoice::Espeak(voice) => voice
-                .speak_to_file(sentence, wav_file_path)
+                .speak_ssml_to_file(ssml, wav_file_path)
                 .map(AnySpeech::Espeak)
                 .map_err(From::from),
             #[cfg(target_os = "macos")]
             AnyVoice::Say(voice) => voice
-                .speak_to_file(sentence, wav_file_path)
+                .speak_ssml_to_file(ssml, wav_file_path)
                 .map(AnySpeech::Say)
                 .map_err(From::from),
         }
     }
 }
 
+impl AnySpeech {
+    /// What the synthesizer wrote to stderr so far.
+    pub fn stderr(&self) -> Stderr {
+        match self {
+            #[cfg(target_os = "windows")]
+            AnySpeech::CScript(speech) => speech.stderr(),
+            AnySpeech::Espeak(speech) => speech.stderr(),
+            #[cfg(target_os = "macos")]
+            AnySpeech::Say(speech) => speech.stderr(),
+        }
+    }
+}
+
 impl crate::Speech for AnySpeech {
     type Error = AnyError;
 
@@ -1,7 +1,9 @@
 pub use err::Error;
+use std::fmt;
+use std::io::Read;
 use std::process::{Child, ExitStatus};
-use std::sync::Mutex;
-use std::thread::sleep;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, sleep, JoinHandle};
 use std::time::Duration;
 
 /// Time between checks in `await_done`
@@ -10,23 +12,164 @@ const AWAIT_DONE_CHECK_INTERVAL: Duration = Duration::from_millis(5);
 const CANCEL_GRACE_TIMEOUT: Duration = Duration::from_millis(300);
 /// Milliseconds to wait on first attempt, then this to the power of two, then three, ...
 const CANCEL_GRACE_WAIT_SLICE_BASE: f64 = 2.3;
+/// Bytes of stderr that are kept, the rest is read and dropped
+const STDERR_CAPACITY: usize = 16 * 1024;
 
 /// Ongoing or finished [`Speech`](trait.Speech.html) in an external process.
 pub struct Speech {
     state: Mutex<State>,
+    stderr: StderrReader,
 }
 
 impl Speech {
-    pub fn new(child: Child) -> Self {
+    /// Tracks the given child process, capturing its stderr if it
+    /// is piped.
+    pub fn new(mut child: Child) -> Self {
+        let stderr = StderrReader::start(&mut child);
+        Speech::with_stderr(child, stderr)
+    }
+
+    /// Tracks the given child process, with a reader that already
+    /// captures its stderr.
+    pub(crate) fn with_stderr(child: Child, stderr: StderrReader) -> Self {
         let mut state = State::Running(child);
         state.update();
 
         Speech {
             state: Mutex::new(state),
+            stderr,
+        }
+    }
+
+    /// What the synthesizer wrote to stderr so far, like warnings
+    /// about unknown voices or invalid SSML.
+    pub fn stderr(&self) -> Stderr {
+        self.stderr.snapshot()
+    }
+}
+
+/// What a synthesizer wrote to stderr, up to a limit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stderr {
+    text: String,
+    truncated: bool,
+}
+
+impl Stderr {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty()
+    }
+
+    /// Whether more was written than was kept.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+impl fmt::Display for Stderr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            write!(f, "(empty)")
+        } else if self.truncated {
+            write!(f, "{} [truncated]", self.text.trim_end())
+        } else {
+            write!(f, "{}", self.text.trim_end())
         }
     }
 }
 
+#[derive(Default)]
+struct Captured {
+    bytes: Vec<u8>,
+    truncated: bool,
+}
+
+/// Reads stderr of a child process on a background thread, so
+/// that the process never blocks on a full pipe.
+pub(crate) struct StderrReader {
+    captured: Arc<Mutex<Captured>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+    /// Whether the output is UTF-16, like that of `cscript //U`
+    wide: bool,
+}
+
+impl StderrReader {
+    /// Starts reading stderr as UTF-8, if it is piped. Otherwise
+    /// nothing is captured.
+    pub fn start(child: &mut Child) -> StderrReader {
+        StderrReader::start_reading(child, false)
+    }
+
+    /// Starts reading stderr as UTF-16 in little endian.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub fn start_wide(child: &mut Child) -> StderrReader {
+        StderrReader::start_reading(child, true)
+    }
+
+    fn start_reading(child: &mut Child, wide: bool) -> StderrReader {
+        let captured = Arc::new(Mutex::new(Captured::default()));
+        let thread = child.stderr.take().map(|mut pipe| {
+            let captured = Arc::clone(&captured);
+            thread::spawn(move || {
+                let mut chunk = [0; 1024];
+                // Ends when the process exits and the pipe is closed
+                while let Ok(len) = pipe.read(&mut chunk) {
+                    if len == 0 {
+                        break;
+                    }
+                    let mut captured = captured.lock().expect("stderr reader panicked");
+                    let kept = len.min(STDERR_CAPACITY - captured.bytes.len());
+                    captured.bytes.extend_from_slice(&chunk[..kept]);
+                    captured.truncated |= kept < len;
+                }
+            })
+        });
+
+        StderrReader {
+            captured,
+            thread: Mutex::new(thread),
+            wide,
+        }
+    }
+
+    /// What was captured so far.
+    pub fn snapshot(&self) -> Stderr {
+        let captured = self.captured.lock().expect("stderr reader panicked");
+        let text = if self.wide {
+            let wide: Vec<u16> = captured
+                .bytes
+                .chunks_exact(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .collect();
+            String::from_utf16_lossy(&wide)
+        } else {
+            String::from_utf8_lossy(&captured.bytes).into_owned()
+        };
+        Stderr {
+            text,
+            truncated: captured.truncated,
+        }
+    }
+
+    /// Waits until stderr is closed, which happens when the
+    /// process has exited, and returns everything captured.
+    pub fn finish(&self) -> Stderr {
+        let thread = self
+            .thread
+            .lock()
+            .expect("Failed to obtain lock on stderr reader")
+            .take();
+        if let Some(thread) = thread {
+            let _ = thread.join();
+        }
+        self.snapshot()
+    }
+}
+
 impl crate::Speech for Speech {
     type Error = Error;
 
@@ -59,13 +202,18 @@ impl crate::Speech for Speech {
     ///
     /// Returns an error on unsuccessful exit status.
     fn is_done(&self) -> Result<bool, Self::Error> {
-        let mut state = self
-            .state
-            .try_lock()
-            .expect("Failed to obtain lock on child process");
+        let exited = {
+            let mut state = self
+                .state
+                .try_lock()
+                .expect("Failed to obtain lock on child process");
+            state.update();
+            state.exited_successfully()
+        };
 
-        state.update();
-        state.exited_successfully()
+        // Without holding the lock, since stderr may stay open for
+        // longer if the process started others that inherited it
+        exited.map_err(|status| Error::exit_failure(status, self.stderr.finish()))
     }
 
     /// Cancels the ongoing speech. Can safely be called
@@ -108,14 +256,16 @@ impl State {
         }
     }
 
-    fn exited_successfully(&self) -> Result<bool, Error> {
+    /// Checks if the process is over, or returns the status of
+    /// an unsuccessful exit.
+    fn exited_successfully(&self) -> Result<bool, ExitStatus> {
         match self {
             State::Running(_) => Ok(false),
             State::Done(status) => {
                 if status.success() {
                     Ok(true)
                 } else {
-                    Err(Error::exit_failure(*status))
+                    Err(*status)
                 }
             }
             State::Cancelled => Ok(true),
@@ -177,6 +327,7 @@ impl State {
 }
 
 mod err {
+    use super::Stderr;
     use failure::{Backtrace, Fail};
     use std::io;
     use std::process::ExitStatus;
@@ -191,9 +342,13 @@ mod err {
             cause: io::Error,
             backtrace: Backtrace,
         },
-        #[fail(display = "speech reported unsuccessful exit: {}", status)]
+        #[fail(
+            display = "speech reported unsuccessful exit: {}, stderr: {}",
+            status, stderr
+        )]
         ExitFailure {
             status: ExitStatus,
+            stderr: Stderr,
             backtrace: Backtrace,
         },
         #[fail(display = "attempt to terminate speech failed")]
@@ -214,9 +369,10 @@ mod err {
             }
         }
 
-        pub fn exit_failure(status: ExitStatus) -> Self {
+        pub fn exit_failure(status: ExitStatus, stderr: Stderr) -> Self {
             Error::ExitFailure {
                 status,
+                stderr,
                 backtrace: Backtrace::new(),
             }
         }
@@ -235,3 +391,47 @@ mod err {
         }
     }
 }
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use crate::Speech as _;
+    use std::process::{Command, Stdio};
+
+    fn sh(script: &str) -> Speech {
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("sh could not be started");
+        Speech::new(child)
+    }
+
+    #[test]
+    fn stderr_in_exit_failure() {
+        let speech = sh("echo 'Unknown voice: klingon' >&2; exit 3");
+        match speech.await_done() {
+            Err(Error::ExitFailure { stderr, .. }) => {
+                assert_eq!(stderr.as_str(), "Unknown voice: klingon\n");
+                assert!(!stderr.is_truncated());
+            }
+            other => panic!("Expected exit failure, got: {:?}", other),
+        }
+        assert_eq!(speech.stderr().to_string(), "Unknown voice: klingon");
+
+        let speech = sh("exit 0");
+        speech.await_done().unwrap();
+        assert!(speech.stderr().is_empty());
+    }
+
+    #[test]
+    fn stderr_capped() {
+        let speech = sh("i=0; while [ $i -lt 400 ]; do echo 'warning: 50 bytes of complaints about the input' >&2; i=$((i+1)); done");
+        speech.await_done().unwrap();
+        let stderr = speech.stderr.finish();
+        assert_eq!(stderr.as_str().len(), STDERR_CAPACITY);
+        assert!(stderr.is_truncated());
+        assert!(stderr.to_string().ends_with("[truncated]"));
+    }
+}
@@ -0,0 +1,374 @@
+//! Speaking long texts one sentence or paragraph at a time,
+//! with a separate [`Speech`](trait.Speech.html) per segment.
+
+pub use err::Error;
+
+use crate::segment::Segment;
+use crate::{Speech, Utterance, Voice};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, sleep};
+use std::time::Duration;
+
+/// Time between checks whether the current segment is done
+const SEGMENT_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A [`Speech`](trait.Speech.html) that speaks the segments of
+/// an utterance one after another in the background, obtained
+/// with [`Voice::speak_chunked`](trait.Voice.html#method.speak_chunked).
+///
+/// Unlike a single speech, it can tell which segment is playing
+/// and skip segments without cancelling the rest.
+pub struct ChunkedSpeech<S: Speech> {
+    segments: Arc<Vec<Segment>>,
+    shared: Arc<Shared<S>>,
+}
+
+struct Shared<S> {
+    state: Mutex<State<S>>,
+    changed: Condvar,
+}
+
+struct State<S> {
+    /// The speech of the current segment, `None` when finished.
+    current: Option<S>,
+    index: usize,
+    /// The segment to continue with instead of the next one.
+    jump_to: Option<usize>,
+    cancelled: bool,
+    failure: Option<(usize, String)>,
+}
+
+impl<S: Speech + Send + 'static> ChunkedSpeech<S> {
+    /// Starts speaking the first segment with the given voice and
+    /// continues with the others in a background thread.
+    pub(crate) fn start<V>(voice: V, utterance: &Utterance) -> Result<ChunkedSpeech<S>, V::Error>
+    where
+        V: Voice<Speech = S> + Send + 'static,
+    {
+        let segments = Arc::new(utterance.segments());
+        let current = match segments.first() {
+            Some(first) => Some(voice.speak_utterance(&first.utterance)?),
+            None => None,
+        };
+
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                current,
+                index: 0,
+                jump_to: None,
+                cancelled: false,
+                failure: None,
+            }),
+            changed: Condvar::new(),
+        });
+
+        if !segments.is_empty() {
+            let segments = Arc::clone(&segments);
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || speak_segments(voice, &segments, &shared));
+        }
+
+        Ok(ChunkedSpeech { segments, shared })
+    }
+}
+
+impl<S: Speech> ChunkedSpeech<S> {
+    /// All segments of the utterance, in the order they are spoken.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// The index of the segment that is currently spoken, or
+    /// `None` if the speech is over.
+    pub fn current_segment(&self) -> Option<usize> {
+        let state = self.shared.lock();
+        state.current.as_ref().map(|_| state.index)
+    }
+
+    /// Stops the current segment and continues with the next.
+    pub fn skip(&self) -> Result<(), Error> {
+        let mut state = self.shared.lock();
+        state.cancel_current()
+    }
+
+    /// Stops the current segment and continues with the segment
+    /// at the given index, which may also be an earlier one.
+    /// Indexes past the last segment end the speech.
+    pub fn skip_to(&self, index: usize) -> Result<(), Error> {
+        let mut state = self.shared.lock();
+        if state.current.is_some() {
+            state.jump_to = Some(index);
+        }
+        state.cancel_current()
+    }
+}
+
+impl<S: Speech> Speech for ChunkedSpeech<S> {
+    type Error = Error;
+
+    /// Waits until all segments have been spoken, or the speech
+    /// has been cancelled or failed.
+    fn await_done(&self) -> Result<(), Self::Error> {
+        let mut state = self.shared.lock();
+        while state.current.is_some() {
+            state = self
+                .shared
+                .changed
+                .wait(state)
+                .expect("Failed to obtain lock on chunked speech");
+        }
+        state.result().map(|_| ())
+    }
+
+    fn is_done(&self) -> Result<bool, Self::Error> {
+        let state = self.shared.lock();
+        state.result().map(|_| state.current.is_none())
+    }
+
+    /// Stops the current segment and skips all that follow.
+    fn cancel(&mut self) -> Result<(), Self::Error> {
+        let mut state = self.shared.lock();
+        state.cancelled = true;
+        state.cancel_current()
+    }
+}
+
+impl<S> Shared<S> {
+    fn lock(&self) -> MutexGuard<'_, State<S>> {
+        self.state
+            .lock()
+            .expect("Failed to obtain lock on chunked speech")
+    }
+}
+
+impl<S: Speech> State<S> {
+    fn cancel_current(&mut self) -> Result<(), Error> {
+        let index = self.index;
+        match self.current.as_mut() {
+            Some(current) => current
+                .cancel()
+                .map_err(|err| Error::segment_failed(index, &err)),
+            None => Ok(()),
+        }
+    }
+
+    fn result(&self) -> Result<(), Error> {
+        match &self.failure {
+            Some((index, message)) => Err(Error::segment_failed(*index, message)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Waits for each segment to end, then starts the next one,
+/// until all are spoken or the speech is cancelled.
+fn speak_segments<V: Voice>(voice: V, segments: &[Segment], shared: &Shared<V::Speech>) {
+    loop {
+        sleep(SEGMENT_CHECK_INTERVAL);
+
+        let mut state = shared.lock();
+        let done = match state.current.as_ref().map(Speech::is_done) {
+            Some(Ok(done)) => done,
+            Some(Err(err)) => {
+                state.failure = Some((state.index, err.to_string()));
+                true
+            }
+            None => return,
+        };
+        if !done {
+            continue;
+        }
+
+        let next = state.jump_to.take().unwrap_or(state.index + 1);
+        if state.cancelled || state.failure.is_some() || next >= segments.len() {
+            state.current = None;
+            shared.changed.notify_all();
+            return;
+        }
+
+        // Starting may take a while, so do not block callers meanwhile.
+        // The finished speech stays current, so waiting goes on.
+        drop(state);
+        let started = voice.speak_utterance(&segments[next].utterance);
+
+        let mut state = shared.lock();
+        match started {
+            Ok(mut speech) => {
+                if state.cancelled || state.jump_to.is_some() {
+                    // Cancelled or skipped while starting, the next
+                    // check finds it done and goes on from there
+                    let _ = speech.cancel();
+                }
+                state.index = next;
+                state.current = Some(speech);
+            }
+            Err(err) => {
+                state.failure = Some((next, err.to_string()));
+                state.current = None;
+            }
+        }
+
+        let finished = state.current.is_none();
+        shared.changed.notify_all();
+        if finished {
+            return;
+        }
+    }
+}
+
+mod err {
+    use failure::{Backtrace, Fail};
+    use std::fmt::Display;
+
+    #[derive(Fail, Debug)]
+    pub enum Error {
+        #[fail(display = "segment {} could not be spoken: {}", segment, message)]
+        SegmentFailed {
+            segment: usize,
+            message: String,
+            backtrace: Backtrace,
+        },
+    }
+
+    impl Error {
+        pub fn segment_failed<E: Display + ?Sized>(segment: usize, cause: &E) -> Self {
+            Error::SegmentFailed {
+                segment,
+                message: cause.to_string(),
+                backtrace: Backtrace::new(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ChildError, EspeakError, Settings};
+    use std::path::Path;
+    use std::time::Instant;
+
+    /// Pretends to speak each utterance for 50ms and records
+    /// the text that was started.
+    #[derive(Clone, Default)]
+    struct RecordingVoice {
+        settings: Settings,
+        spoken: Arc<Mutex<Vec<String>>>,
+        /// How long starting to speak takes.
+        start_delay: Duration,
+    }
+
+    struct RecordedSpeech {
+        end: Mutex<Instant>,
+    }
+
+    impl Voice for RecordingVoice {
+        type Speech = RecordedSpeech;
+        type Error = EspeakError;
+
+        fn settings(&self) -> &Settings {
+            &self.settings
+        }
+
+        fn settings_mut(&mut self) -> &mut Settings {
+            &mut self.settings
+        }
+
+        fn speak_utterance(&self, utterance: &Utterance) -> Result<RecordedSpeech, EspeakError> {
+            let text = utterance
+                .tokens()
+                .iter()
+                .filter_map(|token| match token {
+                    crate::Token::Normal(text) => Some(text.to_string()),
+                    _ => None,
+                })
+                .collect();
+            self.spoken.lock().unwrap().push(text);
+            sleep(self.start_delay);
+            Ok(RecordedSpeech {
+                end: Mutex::new(Instant::now() + Duration::from_millis(50)),
+            })
+        }
+
+        fn speak_utterance_to_file<P>(
+            &self,
+            utterance: &Utterance,
+            _: P,
+        ) -> Result<RecordedSpeech, EspeakError>
+        where
+            P: AsRef<Path>,
+        {
+            self.speak_utterance(utterance)
+        }
+    }
+
+    impl Speech for RecordedSpeech {
+        type Error = ChildError;
+
+        fn await_done(&self) -> Result<(), ChildError> {
+            while !self.is_done()? {
+                sleep(SEGMENT_CHECK_INTERVAL);
+            }
+            Ok(())
+        }
+
+        fn is_done(&self) -> Result<bool, ChildError> {
+            Ok(Instant::now() >= *self.end.lock().unwrap())
+        }
+
+        fn cancel(&mut self) -> Result<(), ChildError> {
+            *self.end.lock().unwrap() = Instant::now();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn speaks_segments_in_order() {
+        let voice = RecordingVoice::default();
+        let speech = voice.speak_chunked("One. Two! Three").unwrap();
+
+        assert_eq!(speech.segments().len(), 3);
+        assert_eq!(speech.current_segment(), Some(0));
+        speech.await_done().unwrap();
+
+        assert!(speech.is_done().unwrap());
+        assert_eq!(speech.current_segment(), None);
+        assert_eq!(*voice.spoken.lock().unwrap(), vec!["One", "Two!", "Three"]);
+    }
+
+    #[test]
+    fn skip_and_cancel() {
+        let voice = RecordingVoice::default();
+        let mut speech = voice.speak_chunked("One. Two. Three. Four").unwrap();
+
+        speech.skip_to(2).unwrap();
+        while speech.current_segment() != Some(2) {
+            sleep(SEGMENT_CHECK_INTERVAL);
+        }
+        speech.cancel().unwrap();
+        speech.await_done().unwrap();
+
+        assert_eq!(*voice.spoken.lock().unwrap(), vec!["One", "Three"]);
+    }
+
+    #[test]
+    fn cancel_while_starting() {
+        let voice = RecordingVoice {
+            start_delay: Duration::from_millis(300),
+            ..RecordingVoice::default()
+        };
+        let mut speech = voice.clone().speak_chunked("One. Two. Three").unwrap();
+        while voice.spoken.lock().unwrap().len() < 2 {
+            sleep(SEGMENT_CHECK_INTERVAL);
+        }
+
+        let asked = Instant::now();
+        assert!(!speech.is_done().unwrap());
+        assert_eq!(speech.current_segment(), Some(0));
+        speech.cancel().unwrap();
+        assert!(asked.elapsed() < voice.start_delay);
+
+        speech.await_done().unwrap();
+        assert_eq!(*voice.spoken.lock().unwrap(), vec!["One", "Two"]);
+    }
+}
@@ -6,8 +6,13 @@
 pub use crate::child::Speech;
 pub use err::Error;
 
-use crate::token::{PauseDuration::*, Token, Tokenizer};
-use crate::version::detect_version_with_arg;
+use crate::child::StderrReader;
+use crate::render;
+use crate::sanitize::{sanitize, Dialect};
+use crate::settings::Settings;
+use crate::version::{detect_version_with_arg, require_version, BackendVersion};
+use crate::voices::{parse_sapi_voices, VoiceInfo};
+use crate::Utterance;
 use script::script_path;
 use std::ffi::OsStr;
 use std::io::Write;
@@ -15,21 +20,95 @@ use std::os::windows::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
 use std::process::{Child, ChildStdin, Command, Stdio};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CScriptVoice {
     script_path: PathBuf,
+    settings: Settings,
+    /// The name of the SAPI voice to speak with, or `None` for
+    /// the default voice of the system.
+    voice: Option<String>,
 }
 
 /// A [`Voice`](trait.Voice.html) that works by opening `cscript`
 /// running a simple VB script that is first placed into an
 /// external file.
 impl CScriptVoice {
+    /// Windows Script Host 5.6 is the oldest version that can
+    /// create the SAPI objects that the script uses.
+    const MINIMUM_VERSION: BackendVersion = BackendVersion::new(5, 6, 0);
+
+    /// Checks that `cscript` is installed in a supported version
+    /// and places the script into a temporary file.
     pub fn new() -> Result<CScriptVoice, Error> {
-        detect_version_with_arg("cscript", None).map_err(Error::cscript_not_installed)?;
+        let banner =
+            detect_version_with_arg("cscript", None).map_err(Error::cscript_not_installed)?;
+        require_version(&banner, &Self::MINIMUM_VERSION).map_err(Error::unsupported_version)?;
 
         let script_path = script_path()?;
 
-        Ok(CScriptVoice { script_path })
+        Ok(CScriptVoice {
+            script_path,
+            settings: Settings::default(),
+            voice: None,
+        })
+    }
+
+    /// Lists the SAPI voices installed on the system.
+    pub fn voices(&self) -> Result<Vec<VoiceInfo>, Error> {
+        const LIST_VOICES_UUID: &str = "1d2ec1fa-5a52-4a26-a0ab-7e6c4bd1f1a5\r\n";
+
+        let mut cscript = Command::new("cscript")
+            .arg("//U")
+            .arg("//NoLogo")
+            .arg(&self.script_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(Error::cannot_invoke)?;
+        let stderr = StderrReader::start_wide(&mut cscript);
+        // Dropping the pipe closes it, so the script exits after listing
+        let written = cscript
+            .stdin
+            .take()
+            .ok_or_else(Error::cannot_open_pipe)
+            .and_then(|mut pipe| write_wide(&mut pipe, OsStr::new(LIST_VOICES_UUID)));
+        if let Err(err) = written {
+            let _ = cscript.kill();
+            let _ = cscript.wait();
+            return Err(err.with_stderr(stderr.finish()));
+        }
+        let output = cscript.wait_with_output().map_err(Error::cannot_invoke)?;
+        if !output.status.success() {
+            return Err(Error::cannot_list_voices(output.status, stderr.finish()));
+        }
+
+        // With //U, the output is UTF-16 as well
+        let wide: Vec<u16> = output
+            .stdout
+            .chunks_exact(2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        Ok(parse_sapi_voices(&String::from_utf16_lossy(&wide)))
+    }
+
+    /// Speaks with the SAPI voice of the given name, like
+    /// `Microsoft Zira Desktop`.
+    ///
+    /// Fails if no such voice is installed, instead of falling
+    /// back to the default voice.
+    pub fn with_voice(mut self, voice: &str) -> Result<CScriptVoice, Error> {
+        if !self.voices()?.iter().any(|listed| listed.id == voice) {
+            return Err(Error::unknown_voice(voice));
+        }
+        self.voice = Some(voice.to_string());
+        Ok(self)
+    }
+
+    /// The name of the SAPI voice, or `None` if the default voice
+    /// of the system is used.
+    pub fn voice(&self) -> Option<&str> {
+        self.voice.as_deref()
     }
 
     fn spawn(&self) -> Result<Child, Error> {
@@ -38,34 +117,63 @@ impl CScriptVoice {
             .arg(&self.script_path)
             .stdin(Stdio::piped())
             .stdout(Stdio::null()) // Ignore standard output
-            .stderr(Stdio::null()) // And error too
+            .stderr(Stdio::piped()) // But keep errors for diagnostics
             .spawn()
             .map_err(Error::cannot_invoke)
     }
 
-    fn invoke_csript<S: AsRef<str>>(
+    fn invoke_csript(
         &self,
-        sentence: S,
+        utterance: &Utterance,
         to_file: Option<&OsStr>,
     ) -> Result<Speech, Error> {
-        let xml = format_sapi_xml(sentence.as_ref());
+        let xml = render::sapi::render(
+            self.settings.prepare(utterance).tokens(),
+            &self.settings.pauses,
+        );
+        match &self.voice {
+            Some(voice) => {
+                let xml = format!(
+                    "<voice required=\"Name={}\">{}</voice>",
+                    sanitize(voice, Dialect::Sapi),
+                    xml
+                );
+                self.invoke_csript_xml(&xml, to_file)
+            }
+            None => self.invoke_csript_xml(&xml, to_file),
+        }
+    }
 
+    fn invoke_csript_xml(&self, xml: &str, to_file: Option<&OsStr>) -> Result<Speech, Error> {
         let mut cscript = self.spawn()?;
-        let mut pipe = cscript.stdin.take().ok_or_else(Error::cannot_open_pipe)?;
-
-        if let Some(file) = to_file {
-            const WRITE_FILE_UUID: &str = "53377b5f-60a2-4c05-a4eb-55de35452a2b\r\n";
-            const NEWLINE: &str = "\r\n";
-            write_wide(&mut pipe, OsStr::new(WRITE_FILE_UUID))?;
-            write_wide(&mut pipe, file)?;
-            write_wide(&mut pipe, OsStr::new(NEWLINE))?;
+        // With //U, stderr is UTF-16 as well
+        let stderr = StderrReader::start_wide(&mut cscript);
+        let written = cscript
+            .stdin
+            .take()
+            .ok_or_else(Error::cannot_open_pipe)
+            .and_then(|mut pipe| {
+                if let Some(file) = to_file {
+                    const WRITE_FILE_UUID: &str = "53377b5f-60a2-4c05-a4eb-55de35452a2b\r\n";
+                    const NEWLINE: &str = "\r\n";
+                    write_wide(&mut pipe, OsStr::new(WRITE_FILE_UUID))?;
+                    write_wide(&mut pipe, file)?;
+                    write_wide(&mut pipe, OsStr::new(NEWLINE))?;
+                }
+
+                write_wide(&mut pipe, OsStr::new(xml))?;
+
+                pipe.flush().map_err(Error::cannot_write)
+            });
+
+        if let Err(err) = written {
+            // Most likely the script failed, e.g. without SAPI
+            let _ = cscript.kill();
+            let _ = cscript.wait();
+            return Err(err.with_stderr(stderr.finish()));
         }
 
-        write_wide(&mut pipe, OsStr::new(&xml))?;
-
-        pipe.flush().map_err(Error::cannot_write)?;
-
-        Ok(Speech::new(cscript))
+        Ok(Speech::with_stderr(cscript, stderr))
     }
 }
 
@@ -73,58 +181,30 @@ impl crate::Voice for CScriptVoice {
     type Speech = Speech;
     type Error = Error;
 
-    /// Speaks the given sentence. Emphasized words can be wrapped in underscores.
-    fn speak<S>(&self, sentence: S) -> Result<Self::Speech, Self::Error>
-    where
-        S: AsRef<str>,
-    {
-        self.invoke_csript(sentence.as_ref(), None)
+    fn settings(&self) -> &Settings {
+        &self.settings
     }
 
-    fn speak_to_file<S, P>(
+    fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    fn speak_utterance(&self, utterance: &Utterance) -> Result<Self::Speech, Self::Error> {
+        self.invoke_csript(utterance, None)
+    }
+
+    fn speak_utterance_to_file<P>(
         &self,
-        sentence: S,
+        utterance: &Utterance,
         wav_file_path: P,
     ) -> Result<Self::Speech, Self::Error>
     where
-        S: AsRef<str>,
         P: AsRef<Path>,
     {
-        self.invoke_csript(sentence.as_ref(), Some(wav_file_path.as_ref().as_os_str()))
+        self.invoke_csript(utterance, Some(wav_file_path.as_ref().as_os_str()))
     }
 }
 
-fn format_sapi_xml(sentence: &str) -> String {
-    let mut xml = String::new();
-
-    xml.push_str("<sapi>");
-    for token in Tokenizer::new(sentence.as_ref()) {
-        match token {
-            Token::Normal(text) => {
-                xml.push_str(" ");
-                xml.push_str(text)
-            }
-            Token::Emphasised(text) => {
-                xml.push_str("<emph>");
-                xml.push_str(text);
-                xml.push_str("</emph>");
-            }
-            Token::Pause(Sentence) => {
-                xml.push_str("<silence msec=\"350\" />");
-            }
-            Token::Pause(Paragraph) => {
-                xml.push_str("<silence msec=\"700\" />");
-            }
-            Token::Pause(Seconds(secs)) => {
-                xml.push_str(&format!("<silence msec=\"{}000\" />", secs));
-            }
-        }
-    }
-    xml.push_str("</sapi>");
-
-    xml
-}
-
 fn write_wide(pipe: &mut ChildStdin, data: &OsStr) -> Result<(), Error> {
     for code_point_16 in data.encode_wide() {
         pipe.write(&char_bytes(code_point_16))
@@ -179,14 +259,19 @@ mod script {
 }
 
 mod err {
+    use crate::child::Stderr;
+    use crate::ssml::Error as SsmlError;
     use crate::version::Error as VersionDetectError;
     use failure::{Backtrace, Fail};
     use std::io::Error as IoError;
+    use std::process::ExitStatus;
 
     #[derive(Debug, Fail)]
     pub enum Error {
         #[fail(display = "cscript command could not be found: {}", _0)]
         CscriptNotInstalled(#[cause] VersionDetectError),
+        #[fail(display = "cscript version is not supported: {}", _0)]
+        UnsupportedVersion(#[cause] VersionDetectError),
         #[fail(
             display = "could not generate speech script for consumption by cscript: {}",
             cause
@@ -202,14 +287,31 @@ mod err {
             cause: IoError,
             backtrace: Backtrace,
         },
-        #[fail(display = "cscript command could not be written to: {}", cause)]
+        #[fail(
+            display = "cscript command could not be written to: {}, stderr: {}",
+            cause, stderr
+        )]
         CannotWrite {
             #[cause]
             cause: IoError,
+            stderr: Stderr,
             backtrace: Backtrace,
         },
         #[fail(display = "cannot open pipe to cscript")]
         CannotOpenPipe(Backtrace),
+        #[fail(display = "invalid SSML: {}", _0)]
+        InvalidSsml(#[cause] SsmlError),
+        #[fail(
+            display = "SAPI voices could not be listed: {}, stderr: {}",
+            status, stderr
+        )]
+        CannotListVoices {
+            status: ExitStatus,
+            stderr: Stderr,
+            backtrace: Backtrace,
+        },
+        #[fail(display = "no SAPI voice {:?} installed", voice)]
+        UnknownVoice { voice: String, backtrace: Backtrace },
     }
 
     impl Error {
@@ -217,6 +319,10 @@ mod err {
             Error::CscriptNotInstalled(cause)
         }
 
+        pub fn unsupported_version(cause: VersionDetectError) -> Self {
+            Error::UnsupportedVersion(cause)
+        }
+
         pub fn cannot_generate_script(cause: IoError) -> Self {
             Error::CannotGenerateScript {
                 cause,
@@ -234,13 +340,54 @@ mod err {
         pub fn cannot_write(cause: IoError) -> Self {
             Error::CannotWrite {
                 cause,
+                stderr: Stderr::default(),
                 backtrace: Backtrace::new(),
             }
         }
 
+        /// Attaches what `cscript` wrote to stderr to errors that
+        /// occur while it is running.
+        pub fn with_stderr(self, stderr: Stderr) -> Self {
+            match self {
+                Error::CannotWrite {
+                    cause, backtrace, ..
+                } => Error::CannotWrite {
+                    cause,
+                    stderr,
+                    backtrace,
+                },
+                other => other,
+            }
+        }
+
         pub fn cannot_open_pipe() -> Self {
             Error::CannotOpenPipe(Backtrace::new())
         }
+
+        pub fn invalid_ssml(cause: SsmlError) -> Self {
+            Error::InvalidSsml(cause)
+        }
+
+        pub fn cannot_list_voices(status: ExitStatus, stderr: Stderr) -> Self {
+            Error::CannotListVoices {
+                status,
+                stderr,
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn unknown_voice(voice: &str) -> Self {
+            Error::UnknownVoice {
+                voice: voice.into(),
+                backtrace: Backtrace::new(),
+            }
+        }
+    }
+
+    impl From<SsmlError> for Error {
+        fn from(cause: SsmlError) -> Self {
+            Error::invalid_ssml(cause)
+        }
     }
 }
 
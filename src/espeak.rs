@@ -2,32 +2,490 @@
 //! commonly available on some linux and unix systems.
 //! On Windows systems it would be rather exotic but
 //! might be available.
+//!
+//! Both `espeak-ng` and the classic `espeak` are supported,
+//! preferring `espeak-ng` where both are installed.
 
 pub use crate::child::Speech;
 pub use err::Error;
 
-use crate::token::{PauseDuration::*, Token, Tokenizer};
-use crate::version::detect_version;
-use crate::Voice;
+use crate::child::StderrReader;
+use crate::phoneme::PhoneticAlphabet;
+use crate::render;
+use crate::settings::Settings;
+use crate::ssml;
+use crate::transcribe::{self, Transcriber, Transcription};
+use crate::version::{
+    detect_version_of, require_version, BackendVersion, Error as VersionDetectError,
+};
+use crate::voices::{parse_espeak_voices, VoiceInfo};
+use crate::{Utterance, Voice};
+use std::env;
+use std::ffi::OsString;
 use std::io::Write;
-use std::path::Path;
-use std::process::{Child, ChildStdin, Command, Stdio};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Output, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+
+/// Environment variable with the path of the `espeak` binary to
+/// use, overriding both the search on the path and the binary set
+/// on the builder.
+const BINARY_OVERRIDE_VAR: &str = "TAVLA_ESPEAK_BIN";
 
 /// A [`Voice`](trait.Voice.html) that works by opening
 /// a shell and piping text into `espeak`.
-#[derive(Debug)]
-pub struct Espeak;
+#[derive(Debug, Clone)]
+pub struct Espeak {
+    settings: Settings,
+    /// How to start the `espeak` that was found.
+    program: Program,
+    flavor: EspeakFlavor,
+    /// The version reported by `--version`, if it could be parsed.
+    version: Option<BackendVersion>,
+    features: EspeakFeatures,
+    /// The voice passed with `-v`, like `de+f3`.
+    voice: Option<String>,
+    /// The voice for the locale, looked up on first use if no
+    /// voice was set.
+    locale_voice: OnceLock<Option<String>>,
+    options: EspeakOptions,
+}
+
+/// Configures how `espeak` is started, e.g. to use a bundled
+/// build with its own data directory instead of the one on the
+/// path.
+///
+/// ```no_run
+/// # use tavla::Espeak;
+/// # fn main() -> Result<(), tavla::EspeakError> {
+/// let espeak = Espeak::builder()
+///     .binary("/opt/speech/bin/espeak-ng")
+///     .data_path("/opt/speech/share")
+///     .env("ESPEAK_DATA_PATH", "/opt/speech/share")
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EspeakBuilder {
+    binary: Option<PathBuf>,
+    env: Vec<(OsString, OsString)>,
+    data_path: Option<PathBuf>,
+    working_dir: Option<PathBuf>,
+}
+
+impl EspeakBuilder {
+    /// A builder that looks for `espeak-ng` and `espeak` on the
+    /// path, with the environment of this process.
+    pub fn new() -> EspeakBuilder {
+        EspeakBuilder::default()
+    }
+
+    /// Uses the `espeak` or `espeak-ng` binary at the given path,
+    /// instead of looking for one on the path. `TAVLA_ESPEAK_BIN`
+    /// takes precedence, if set.
+    pub fn binary<P: Into<PathBuf>>(mut self, path: P) -> EspeakBuilder {
+        self.binary = Some(path.into());
+        self
+    }
+
+    /// Sets an environment variable for `espeak`, like
+    /// `ESPEAK_DATA_PATH`, in addition to the environment of
+    /// this process.
+    pub fn env<K, V>(mut self, key: K, value: V) -> EspeakBuilder
+    where
+        K: Into<OsString>,
+        V: Into<OsString>,
+    {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Passes the directory containing the data of `espeak` with
+    /// `--path`.
+    pub fn data_path<P: Into<PathBuf>>(mut self, path: P) -> EspeakBuilder {
+        self.data_path = Some(path.into());
+        self
+    }
+
+    /// Starts `espeak` in the given working directory.
+    pub fn working_dir<P: Into<PathBuf>>(mut self, dir: P) -> EspeakBuilder {
+        self.working_dir = Some(dir.into());
+        self
+    }
+
+    /// Finds `espeak` and checks its version, like
+    /// [`Espeak::new`](struct.Espeak.html#method.new) does.
+    pub fn build(self) -> Result<Espeak, Error> {
+        self.build_with_override(env::var_os(BINARY_OVERRIDE_VAR))
+    }
+
+    /// Builds with the given value of the override variable, so
+    /// that tests do not depend on the environment.
+    fn build_with_override(self, override_binary: Option<OsString>) -> Result<Espeak, Error> {
+        let binaries = binaries(override_binary, self.binary.clone());
+        let programs = binaries.into_iter().map(|binary| Program {
+            binary,
+            env: self.env.clone(),
+            data_path: self.data_path.clone(),
+            working_dir: self.working_dir.clone(),
+        });
+        let (program, version) = detect(programs).map_err(Error::espeak_not_installed)?;
+
+        let flavor = EspeakFlavor::from_version(&String::from_utf8_lossy(&version.stdout));
+        let version = require_version(&version, &flavor.minimum_version())
+            .map_err(Error::unsupported_version)?;
+        let help = program
+            .command()
+            .arg("--help")
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default();
+        Ok(Espeak {
+            settings: Settings::default(),
+            program,
+            flavor,
+            version,
+            features: EspeakFeatures::detect(flavor, &help),
+            voice: None,
+            locale_voice: OnceLock::new(),
+            options: EspeakOptions::default(),
+        })
+    }
+}
+
+/// The binaries to try in order: the one from the override
+/// variable, the one set explicitly, or else `espeak-ng` and
+/// `espeak` on the path.
+fn binaries(override_binary: Option<OsString>, binary: Option<PathBuf>) -> Vec<PathBuf> {
+    match override_binary
+        .filter(|binary| !binary.is_empty())
+        .map(PathBuf::from)
+        .or(binary)
+    {
+        Some(binary) => vec![binary],
+        None => vec!["espeak-ng".into(), "espeak".into()],
+    }
+}
+
+/// Finds the first of the programs that reports a version,
+/// or returns the error of the last one.
+fn detect<I>(programs: I) -> Result<(Program, Output), VersionDetectError>
+where
+    I: IntoIterator<Item = Program>,
+{
+    let mut error = None;
+    for program in programs {
+        match detect_version_of(program.command().arg("--version")) {
+            Ok(output) => return Ok((program, output)),
+            Err(err) => error = Some(err),
+        }
+    }
+    Err(error.expect("there is always a binary to try"))
+}
+
+/// How to start `espeak`: the binary with extra environment,
+/// data directory and working directory.
+#[derive(Debug, Clone)]
+struct Program {
+    binary: PathBuf,
+    env: Vec<(OsString, OsString)>,
+    data_path: Option<PathBuf>,
+    working_dir: Option<PathBuf>,
+}
+
+impl Program {
+    /// A command for the binary, with everything but the
+    /// arguments for the task at hand.
+    fn command(&self) -> Command {
+        let mut cmd = Command::new(&self.binary);
+        cmd.envs(self.env.iter().map(|(key, value)| (key, value)));
+        if let Some(dir) = &self.working_dir {
+            cmd.current_dir(dir);
+        }
+        if let Some(path) = &self.data_path {
+            let mut arg = OsString::from("--path=");
+            arg.push(path);
+            cmd.arg(arg);
+        }
+        cmd
+    }
+}
+
+/// Which implementation of `espeak` was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EspeakFlavor {
+    /// The original `espeak`, which is no longer developed.
+    Espeak,
+    /// `espeak-ng`, the maintained fork of `espeak`, also when
+    /// installed under the name `espeak`.
+    EspeakNg,
+}
+
+impl EspeakFlavor {
+    /// Tells the flavors apart by the output of `--version`, which
+    /// starts with `eSpeak NG` for `espeak-ng`.
+    fn from_version(version: &str) -> EspeakFlavor {
+        if version.to_lowercase().contains("espeak ng") {
+            EspeakFlavor::EspeakNg
+        } else {
+            EspeakFlavor::Espeak
+        }
+    }
+
+    /// The oldest version that is supported. `espeak` before 1.46
+    /// lacks `--ipa` and mishandles some SSML, and 1.49 is the first
+    /// release of `espeak-ng`.
+    pub fn minimum_version(self) -> BackendVersion {
+        match self {
+            EspeakFlavor::Espeak => BackendVersion::new(1, 46, 0),
+            EspeakFlavor::EspeakNg => BackendVersion::new(1, 49, 0),
+        }
+    }
+}
+
+/// What the installed `espeak` supports beyond the basics that
+/// all versions have in common.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EspeakFeatures {
+    /// Whether `--ipa` can output phonemes in IPA, which older
+    /// versions of `espeak` lack.
+    pub ipa: bool,
+    /// Whether IPA in `<phoneme>` elements of SSML is spoken.
+    /// Classic `espeak` ignores the element and speaks its text.
+    pub ssml_phonemes: bool,
+}
+
+impl EspeakFeatures {
+    /// Derives the features from the flavor and the options listed
+    /// in the output of `--help`.
+    fn detect(flavor: EspeakFlavor, help: &str) -> EspeakFeatures {
+        EspeakFeatures {
+            ipa: help.contains("--ipa"),
+            ssml_phonemes: flavor == EspeakFlavor::EspeakNg,
+        }
+    }
+}
+
+/// Rate, pitch, amplitude and gaps between words of `espeak`,
+/// checked to be in the ranges that `espeak` accepts.
+///
+/// Options that are not set are left to `espeak`.
+///
+/// ```
+/// # use tavla::EspeakOptions;
+/// # fn main() -> Result<(), tavla::EspeakError> {
+/// let options = EspeakOptions::new().speed(220)?.pitch(30)?;
+/// assert!(EspeakOptions::new().amplitude(500).is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EspeakOptions {
+    speed: Option<u32>,
+    pitch: Option<u32>,
+    amplitude: Option<u32>,
+    word_gap: Option<u32>,
+}
+
+impl EspeakOptions {
+    const SPEED: RangeInclusive<u32> = 80..=450;
+    const PITCH: RangeInclusive<u32> = 0..=99;
+    const AMPLITUDE: RangeInclusive<u32> = 0..=200;
+    const WORD_GAP: RangeInclusive<u32> = 0..=100;
+
+    /// Options that leave everything to `espeak`.
+    pub fn new() -> EspeakOptions {
+        EspeakOptions::default()
+    }
+
+    /// Sets the speed in words per minute, from 80 to 450,
+    /// passed as `-s`. `espeak` uses 175 by default.
+    pub fn speed(mut self, words_per_minute: u32) -> Result<EspeakOptions, Error> {
+        self.speed = Some(checked("speed", words_per_minute, Self::SPEED)?);
+        Ok(self)
+    }
+
+    /// Sets the pitch from 0 to 99, passed as `-p`. `espeak`
+    /// uses 50 by default.
+    pub fn pitch(mut self, pitch: u32) -> Result<EspeakOptions, Error> {
+        self.pitch = Some(checked("pitch", pitch, Self::PITCH)?);
+        Ok(self)
+    }
+
+    /// Sets the amplitude from 0 to 200, passed as `-a`. `espeak`
+    /// uses 100 by default.
+    pub fn amplitude(mut self, amplitude: u32) -> Result<EspeakOptions, Error> {
+        self.amplitude = Some(checked("amplitude", amplitude, Self::AMPLITUDE)?);
+        Ok(self)
+    }
+
+    /// Sets an additional pause between words in units of 10ms
+    /// at the default speed, up to 100, passed as `-g`.
+    pub fn word_gap(mut self, word_gap: u32) -> Result<EspeakOptions, Error> {
+        self.word_gap = Some(checked("word gap", word_gap, Self::WORD_GAP)?);
+        Ok(self)
+    }
+
+    /// The command line arguments for the options that are set.
+    fn args(&self) -> Vec<String> {
+        [
+            ("-s", self.speed),
+            ("-p", self.pitch),
+            ("-a", self.amplitude),
+            ("-g", self.word_gap),
+        ]
+        .iter()
+        .filter_map(|(flag, value)| value.map(|value| vec![flag.to_string(), value.to_string()]))
+        .flatten()
+        .collect()
+    }
+}
+
+fn checked(option: &'static str, value: u32, range: RangeInclusive<u32>) -> Result<u32, Error> {
+    if range.contains(&value) {
+        Ok(value)
+    } else {
+        Err(Error::option_out_of_range(option, value, range))
+    }
+}
 
 impl Espeak {
+    /// Obtains `espeak-ng`, or `espeak` if it is not installed,
+    /// with the voice for the language of the locale in `LC_ALL`,
+    /// `LC_MESSAGES` or `LANG`, or the default voice of `espeak`
+    /// if it has none for it.
+    ///
+    /// Fails if the version is older than the
+    /// [minimum version](enum.EspeakFlavor.html#method.minimum_version)
+    /// of its flavor.
+    ///
+    /// The binary in the `TAVLA_ESPEAK_BIN` environment variable is
+    /// used instead, if set.
     pub fn new() -> Result<Espeak, Error> {
-        detect_version("espeak").map_err(Error::espeak_not_installed)?;
-        Ok(Espeak)
+        EspeakBuilder::new().build()
+    }
+
+    /// Configures the binary, environment and directories to start
+    /// `espeak` with.
+    pub fn builder() -> EspeakBuilder {
+        EspeakBuilder::new()
+    }
+
+    /// Whether `espeak-ng` or the classic `espeak` was found.
+    pub fn flavor(&self) -> EspeakFlavor {
+        self.flavor
+    }
+
+    /// The version of `espeak`, or `None` if it reported none
+    /// that could be parsed.
+    pub fn version(&self) -> Option<&BackendVersion> {
+        self.version.as_ref()
+    }
+
+    /// What the found `espeak` supports, to adapt to it.
+    pub fn features(&self) -> EspeakFeatures {
+        self.features
+    }
+
+    /// Speaks with the given voice, which is a language like `de`
+    /// or `en-us`, or the name of a voice, and an optional variant
+    /// like `f3` or `whisper`. The voice may also include the
+    /// variant, like `de+f3`.
+    ///
+    /// Fails if `espeak` knows no such voice or variant, instead
+    /// of falling back to the default voice.
+    pub fn with_voice(mut self, voice: &str, variant: Option<&str>) -> Result<Espeak, Error> {
+        let (voice, variant) = match (variant, voice.split_once('+')) {
+            (None, Some((voice, variant))) => (voice, Some(variant)),
+            _ => (voice, variant),
+        };
+
+        if !is_listed(&list_voices(&self.program, None)?, voice) {
+            return Err(Error::unknown_voice(voice));
+        }
+        self.voice = Some(match variant {
+            Some(variant) => {
+                if !is_listed(&list_voices(&self.program, Some("variant"))?, variant) {
+                    return Err(Error::unknown_variant(variant));
+                }
+                format!("{}+{}", voice, variant)
+            }
+            None => voice.to_string(),
+        });
+
+        Ok(self)
+    }
+
+    /// The voice passed to `espeak`, like `de+f3`, or `None` if
+    /// `espeak` uses its default voice.
+    ///
+    /// Without a voice set, this is the voice for the locale of
+    /// the system, which is looked up the first time it is needed.
+    pub fn voice(&self) -> Option<&str> {
+        self.voice.as_deref().or_else(|| {
+            self.locale_voice
+                .get_or_init(|| {
+                    list_voices(&self.program, None)
+                        .ok()
+                        .and_then(|voices| locale_voice(&voices))
+                })
+                .as_deref()
+        })
+    }
+
+    /// Lists the voices that `espeak` has installed. Their `id`
+    /// can be passed to [`with_voice`](#method.with_voice).
+    pub fn voices(&self) -> Result<Vec<VoiceInfo>, Error> {
+        Ok(parse_espeak_voices(&run_listing(
+            &self.program,
+            "--voices",
+        )?))
+    }
+
+    /// Speaks with the given speed, pitch, amplitude and gaps
+    /// between words.
+    pub fn with_options(mut self, options: EspeakOptions) -> Espeak {
+        self.options = options;
+        self
+    }
+
+    pub fn options(&self) -> &EspeakOptions {
+        &self.options
+    }
+
+    /// Checks that the given phonemes only use symbols of IPA or
+    /// of the mnemonics of `espeak`, without running `espeak`.
+    ///
+    /// Use this to catch typos in pronunciations of names before
+    /// putting them into an utterance, where invalid phonemes
+    /// silently fall back to the written text. To see what `espeak`
+    /// makes of them, [`transcribe`](trait.Transcriber.html#method.transcribe)
+    /// the utterance.
+    pub fn validate_phonemes(
+        &self,
+        alphabet: PhoneticAlphabet,
+        phonemes: &str,
+    ) -> Result<(), Error> {
+        match alphabet {
+            PhoneticAlphabet::Ipa | PhoneticAlphabet::Espeak => {
+                alphabet.validate(phonemes).map_err(Error::invalid_phonemes)
+            }
+            other => Err(Error::unsupported_alphabet(other)),
+        }
     }
 
     fn open_espeak(&self, output_wav_path: Option<&Path>) -> Result<Child, Error> {
-        let mut cmd = Command::new("espeak");
+        let mut cmd = self.program.command();
 
         cmd.arg("-m");
+        if let Some(voice) = self.voice() {
+            cmd.arg("-v");
+            cmd.arg(voice);
+        }
+        cmd.args(self.options.args());
         if let Some(output_wav) = output_wav_path {
             cmd.arg("-w");
             cmd.arg(output_wav);
@@ -39,42 +497,111 @@ impl Espeak {
     fn invoke(&self, cmd: &mut Command) -> Result<Child, Error> {
         cmd.stdin(Stdio::piped())
             .stdout(Stdio::null()) // Ignore standard output
-            .stderr(Stdio::null()) // And error too
+            .stderr(Stdio::piped()) // But keep errors for diagnostics
             .spawn()
             .map_err(Error::cannot_invoke)
     }
 
-    fn speak(&self, sentence: &str, output_wav_path: Option<&Path>) -> Result<Speech, Error> {
+    fn speak(
+        &self,
+        utterance: &Utterance,
+        output_wav_path: Option<&Path>,
+    ) -> Result<Speech, Error> {
+        let ssml = render::ssml::render(
+            self.settings.prepare(utterance).tokens(),
+            &self.settings.pauses,
+        );
+        self.pipe_ssml(&ssml, output_wav_path)
+    }
+
+    /// Validates the given SSML document, then passes it to
+    /// `espeak` as is.
+    fn speak_ssml_unchanged(
+        &self,
+        ssml: &str,
+        output_wav_path: Option<&Path>,
+    ) -> Result<Speech, Error> {
+        ssml::parse(ssml)?;
+        self.pipe_ssml(ssml, output_wav_path)
+    }
+
+    fn pipe_ssml(&self, ssml: &str, output_wav_path: Option<&Path>) -> Result<Speech, Error> {
         let mut espeak = self.open_espeak(output_wav_path)?;
-        espeak
+        let stderr = StderrReader::start(&mut espeak);
+        self.write_ssml_to(&mut espeak, &stderr, ssml)?;
+        Ok(Speech::with_stderr(espeak, stderr))
+    }
+
+    /// Writes the SSML into the pipe of the started `espeak`. If
+    /// that fails, `espeak` is stopped and the error gets what it
+    /// wrote to stderr.
+    fn write_ssml_to(
+        &self,
+        espeak: &mut Child,
+        stderr: &StderrReader,
+        ssml: &str,
+    ) -> Result<(), Error> {
+        let written = espeak
             .stdin
             .take()
             .ok_or_else(Error::cannot_open_pipe)
-            .and_then(|p| self.write_ssml_to_pipe(sentence.as_ref(), p))
-            .map(|_| Speech::new(espeak))
+            .and_then(|pipe| Espeak::write_ssml_to_pipe(ssml, pipe));
+        if let Err(err) = written {
+            // Most likely espeak exited early, e.g. on invalid arguments
+            let _ = espeak.kill();
+            let _ = espeak.wait();
+            return Err(err.with_stderr(stderr.finish()));
+        }
+        Ok(())
     }
 
-    fn write_ssml_to_pipe(&self, raw_text: &str, mut pipe: ChildStdin) -> Result<(), Error> {
-        write!(pipe, "<speak>").map_err(Error::cannot_write)?;
-        for token in Tokenizer::new(raw_text.trim()) {
-            match token {
-                Token::Normal(text) => write!(pipe, " {}", text).map_err(Error::cannot_write)?,
-                Token::Emphasised(text) => {
-                    write!(pipe, "<emphasis> {}</emphasis>", text).map_err(Error::cannot_write)?
-                }
-                Token::Pause(Sentence) => {
-                    write!(pipe, "<break strength=\"medium\"/>").map_err(Error::cannot_write)?
-                }
-                Token::Pause(Paragraph) => {
-                    write!(pipe, "<break strength=\"x-strong\"/>").map_err(Error::cannot_write)?
-                }
-                Token::Pause(Seconds(secs)) => {
-                    write!(pipe, "<break time=\"{}s\"/>", secs).map_err(Error::cannot_write)?
-                }
+    fn write_ssml_to_pipe(ssml: &str, mut pipe: ChildStdin) -> Result<(), Error> {
+        writeln!(pipe, "{}", ssml).map_err(Error::cannot_write)?;
+        pipe.flush().map_err(Error::cannot_write)
+    }
+
+    /// Has `espeak` print the phonemes of the SSML document with
+    /// `-x` or `--ipa` instead of playing them, one clause per line.
+    fn transcribe_ssml(&self, ssml: String, alphabet: PhoneticAlphabet) -> Result<String, Error> {
+        let flag = match alphabet {
+            PhoneticAlphabet::Espeak => "-x",
+            PhoneticAlphabet::Ipa if self.features.ipa => "--ipa",
+            other => return Err(Error::unsupported_alphabet(other)),
+        };
+
+        let mut cmd = self.program.command();
+        cmd.arg("-q").arg("-m").arg(flag);
+        if let Some(voice) = self.voice() {
+            cmd.arg("-v");
+            cmd.arg(voice);
+        }
+        let mut espeak = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(Error::cannot_invoke)?;
+        let stderr = StderrReader::start(&mut espeak);
+
+        let pipe = match espeak.stdin.take() {
+            Some(pipe) => pipe,
+            None => {
+                let _ = espeak.kill();
+                let _ = espeak.wait();
+                return Err(Error::cannot_open_pipe());
             }
+        };
+        // espeak prints phonemes while it reads, so write on another
+        // thread while stdout is read here, or both block on full pipes
+        let writer = thread::spawn(move || Espeak::write_ssml_to_pipe(&ssml, pipe));
+        let output = espeak.wait_with_output().map_err(Error::cannot_invoke)?;
+        let written = writer.join().expect("Failed to write to espeak");
+        if !output.status.success() {
+            return Err(Error::transcription_failed(output.status, stderr.finish()));
         }
-        writeln!(pipe, "</speak>").map_err(Error::cannot_write)?;
-        pipe.flush().map_err(Error::cannot_write)
+        written.map_err(|err| err.with_stderr(stderr.finish()))?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
     }
 }
 
@@ -82,37 +609,210 @@ impl Voice for Espeak {
     type Speech = Speech;
     type Error = Error;
 
-    fn speak<S>(&self, sentence: S) -> Result<Self::Speech, Self::Error>
+    fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    fn speak_utterance(&self, utterance: &Utterance) -> Result<Self::Speech, Self::Error> {
+        self.speak(utterance, None)
+    }
+
+    fn speak_utterance_to_file<P>(
+        &self,
+        utterance: &Utterance,
+        wav_file_path: P,
+    ) -> Result<Self::Speech, Self::Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.speak(utterance, Some(wav_file_path.as_ref()))
+    }
+
+    /// Speaks the given SSML document after validating it,
+    /// passing it to `espeak` unchanged.
+    fn speak_ssml<S>(&self, ssml: S) -> Result<Self::Speech, Self::Error>
     where
         S: AsRef<str>,
     {
-        self.speak(sentence.as_ref(), None)
+        self.speak_ssml_unchanged(ssml.as_ref(), None)
     }
 
-    fn speak_to_file<S, P>(
+    fn speak_ssml_to_file<S, P>(
         &self,
-        sentence: S,
+        ssml: S,
         wav_file_path: P,
     ) -> Result<Self::Speech, Self::Error>
     where
         S: AsRef<str>,
         P: AsRef<Path>,
     {
-        self.speak(sentence.as_ref(), Some(wav_file_path.as_ref()))
+        self.speak_ssml_unchanged(ssml.as_ref(), Some(wav_file_path.as_ref()))
+    }
+}
+
+/// Transcribes with `espeak -q -x`, or `--ipa` for IPA, which
+/// requires [`EspeakFeatures::ipa`](struct.EspeakFeatures.html#structfield.ipa).
+/// Other alphabets are not supported.
+impl Transcriber for Espeak {
+    type Error = Error;
+
+    fn transcribe_utterance(
+        &self,
+        utterance: &Utterance,
+        alphabet: PhoneticAlphabet,
+    ) -> Result<String, Self::Error> {
+        let ssml = render::ssml::render(
+            self.settings.prepare(utterance).tokens(),
+            &self.settings.pauses,
+        );
+        let output = self.transcribe_ssml(ssml, alphabet)?;
+        Ok(transcribe::join_clauses(&output))
+    }
+
+    /// Transcribes all words in a single run of `espeak`, with
+    /// every word in a sentence of its own, so that `espeak` prints
+    /// the phonemes of each on a line of its own.
+    fn transcribe_words(
+        &self,
+        utterance: &Utterance,
+        alphabet: PhoneticAlphabet,
+    ) -> Result<Vec<Transcription>, Self::Error> {
+        let words = transcribe::words(self.settings.prepare(utterance).tokens());
+        let mut ssml = String::from("<speak>\n");
+        for (_, token) in &words {
+            let rendered = render::ssml::render(std::slice::from_ref(token), &self.settings.pauses);
+            let rendered = rendered
+                .strip_prefix("<speak>")
+                .and_then(|rendered| rendered.strip_suffix("</speak>"))
+                .unwrap_or(&rendered);
+            ssml.push_str(&format!("<s>{}</s>\n", rendered));
+        }
+        ssml.push_str("</speak>");
+
+        let output = self.transcribe_ssml(ssml, alphabet)?;
+        let lines: Vec<&str> = output
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        if lines.len() != words.len() {
+            return Err(Error::transcription_mismatch(words.len(), lines.len()));
+        }
+        Ok(words
+            .into_iter()
+            .zip(lines)
+            .map(|((word, _), phonemes)| Transcription {
+                word,
+                phonemes: phonemes.to_string(),
+            })
+            .collect())
+    }
+}
+
+/// Lists the names that select a voice with `-v`, or a variant
+/// with `--voices=variant`, in lowercase.
+fn list_voices(program: &Program, kind: Option<&str>) -> Result<Vec<String>, Error> {
+    let arg = match kind {
+        Some(kind) => format!("--voices={}", kind),
+        None => "--voices".to_string(),
+    };
+    Ok(voice_names(&run_listing(program, &arg)?))
+}
+
+/// Runs `espeak` with an argument that makes it list voices and
+/// returns the listing.
+fn run_listing(program: &Program, arg: &str) -> Result<String, Error> {
+    let mut espeak = program
+        .command()
+        .arg(arg)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(Error::cannot_invoke)?;
+    let stderr = StderrReader::start(&mut espeak);
+    let output = espeak.wait_with_output().map_err(Error::cannot_invoke)?;
+    if !output.status.success() {
+        return Err(Error::cannot_list_voices(output.status, stderr.finish()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The languages, names and files of the voices in the output
+/// of `espeak --voices`, which all select a voice.
+fn voice_names(listing: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for voice in parse_espeak_voices(listing) {
+        let file_name = voice.id.rsplit('/').next().unwrap_or(&voice.id);
+        names.push(file_name.to_lowercase());
+        names.push(voice.id.to_lowercase());
+        names.push(voice.name.to_lowercase());
+        names.extend(
+            voice
+                .languages
+                .iter()
+                .map(|language| language.to_lowercase()),
+        );
+    }
+    names
+}
+
+fn is_listed(names: &[String], name: &str) -> bool {
+    names.contains(&name.to_lowercase())
+}
+
+/// The voice for the language of the locale in the environment,
+/// if `espeak` has one.
+fn locale_voice(names: &[String]) -> Option<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|locale| !locale.is_empty())?;
+    voice_for_locale(&locale, names)
+}
+
+/// Finds a voice for a locale like `de_AT.UTF-8`, preferring one
+/// for the region, like `de-at`, over one for the language.
+fn voice_for_locale(locale: &str, names: &[String]) -> Option<String> {
+    let tag = locale
+        .split(['.', '@'])
+        .next()?
+        .replace('_', "-")
+        .to_lowercase();
+    let language = tag.split('-').next()?;
+    if language.is_empty() || tag == "c" || tag == "posix" {
+        return None;
     }
+
+    [tag.as_str(), language]
+        .iter()
+        .find(|candidate| is_listed(names, candidate))
+        .map(|voice| voice.to_string())
 }
 
 mod err {
+    use crate::child::Stderr;
+    use crate::phoneme::{Error as PhonemeError, PhoneticAlphabet};
+    use crate::ssml::Error as SsmlError;
     use crate::version::Error as VersionDetectError;
     use failure::{Backtrace, Fail};
     use std::io;
+    use std::ops::RangeInclusive;
+    use std::process::ExitStatus;
 
     /// `Espeak`-specific errors during any phase of IO.
     #[derive(Fail, Debug)]
     pub enum Error {
-        /// No `espeak` on path.
+        /// Neither `espeak-ng` nor `espeak` on path.
         #[fail(display = "espeak executable could not be found: {}", _0)]
         EspeakNotInstalled(#[cause] VersionDetectError),
+        /// `espeak` is older than the minimum version.
+        #[fail(display = "espeak version is not supported: {}", _0)]
+        UnsupportedVersion(#[cause] VersionDetectError),
         #[fail(display = "espeak could not be started: {}", cause)]
         CannotInvoke {
             #[cause]
@@ -121,10 +821,67 @@ mod err {
         },
         #[fail(display = "pipe to espeak could not be opened")]
         CannotOpenPipe { backtrace: Backtrace },
-        #[fail(display = "pipe to espeak cannot be written: {}", cause)]
+        #[fail(
+            display = "pipe to espeak cannot be written: {}, stderr: {}",
+            cause, stderr
+        )]
         CannotWrite {
             #[cause]
             cause: io::Error,
+            stderr: Stderr,
+            backtrace: Backtrace,
+        },
+        #[fail(display = "invalid SSML: {}", _0)]
+        InvalidSsml(#[cause] SsmlError),
+        #[fail(display = "invalid phonemes: {}", _0)]
+        InvalidPhonemes(#[cause] PhonemeError),
+        #[fail(display = "espeak does not understand {:?} phonemes", alphabet)]
+        UnsupportedAlphabet {
+            alphabet: PhoneticAlphabet,
+            backtrace: Backtrace,
+        },
+        #[fail(
+            display = "voices of espeak could not be listed: {}, stderr: {}",
+            status, stderr
+        )]
+        CannotListVoices {
+            status: ExitStatus,
+            stderr: Stderr,
+            backtrace: Backtrace,
+        },
+        #[fail(display = "espeak has no voice {:?}", voice)]
+        UnknownVoice { voice: String, backtrace: Backtrace },
+        #[fail(display = "espeak has no voice variant {:?}", variant)]
+        UnknownVariant {
+            variant: String,
+            backtrace: Backtrace,
+        },
+        #[fail(
+            display = "espeak could not transcribe the text: {}, stderr: {}",
+            status, stderr
+        )]
+        TranscriptionFailed {
+            status: ExitStatus,
+            stderr: Stderr,
+            backtrace: Backtrace,
+        },
+        #[fail(
+            display = "espeak printed {} lines of phonemes for {} words",
+            lines, words
+        )]
+        TranscriptionMismatch {
+            words: usize,
+            lines: usize,
+            backtrace: Backtrace,
+        },
+        #[fail(
+            display = "espeak {} must be in {:?}, but was {}",
+            option, range, value
+        )]
+        OptionOutOfRange {
+            option: &'static str,
+            value: u32,
+            range: RangeInclusive<u32>,
             backtrace: Backtrace,
         },
     }
@@ -134,6 +891,10 @@ mod err {
             Error::EspeakNotInstalled(cause)
         }
 
+        pub fn unsupported_version(cause: VersionDetectError) -> Self {
+            Error::UnsupportedVersion(cause)
+        }
+
         pub fn cannot_invoke(cause: io::Error) -> Self {
             Error::CannotInvoke {
                 cause,
@@ -150,8 +911,337 @@ mod err {
         pub fn cannot_write(cause: io::Error) -> Self {
             Error::CannotWrite {
                 cause,
+                stderr: Stderr::default(),
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        /// Attaches what `espeak` wrote to stderr to errors that
+        /// occur while it is running.
+        pub fn with_stderr(self, stderr: Stderr) -> Self {
+            match self {
+                Error::CannotWrite {
+                    cause, backtrace, ..
+                } => Error::CannotWrite {
+                    cause,
+                    stderr,
+                    backtrace,
+                },
+                other => other,
+            }
+        }
+
+        pub fn invalid_ssml(cause: SsmlError) -> Self {
+            Error::InvalidSsml(cause)
+        }
+
+        pub fn invalid_phonemes(cause: PhonemeError) -> Self {
+            Error::InvalidPhonemes(cause)
+        }
+
+        pub fn unsupported_alphabet(alphabet: PhoneticAlphabet) -> Self {
+            Error::UnsupportedAlphabet {
+                alphabet,
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn cannot_list_voices(status: ExitStatus, stderr: Stderr) -> Self {
+            Error::CannotListVoices {
+                status,
+                stderr,
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn unknown_voice(voice: &str) -> Self {
+            Error::UnknownVoice {
+                voice: voice.into(),
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn unknown_variant(variant: &str) -> Self {
+            Error::UnknownVariant {
+                variant: variant.into(),
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn transcription_failed(status: ExitStatus, stderr: Stderr) -> Self {
+            Error::TranscriptionFailed {
+                status,
+                stderr,
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn transcription_mismatch(words: usize, lines: usize) -> Self {
+            Error::TranscriptionMismatch {
+                words,
+                lines,
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn option_out_of_range(
+            option: &'static str,
+            value: u32,
+            range: RangeInclusive<u32>,
+        ) -> Self {
+            Error::OptionOutOfRange {
+                option,
+                value,
+                range,
                 backtrace: Backtrace::new(),
             }
         }
     }
+
+    impl From<SsmlError> for Error {
+        fn from(cause: SsmlError) -> Self {
+            Error::invalid_ssml(cause)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Speech as _;
+
+    const VOICES: &str = "Pty Language Age/Gender VoiceName          File          Other Languages
+ 5  af             M  afrikaans            other/af
+ 5  de             M  german               de
+ 5  en-gb          M  english              default       (en 2)
+ 5  en-us          M  english-us           en-us         (en-r 5)(en 3)
+";
+
+    #[test]
+    fn voices_by_language_name_and_file() {
+        let names = voice_names(VOICES);
+        for name in &[
+            "af",
+            "Afrikaans",
+            "other/af",
+            "de",
+            "en-gb",
+            "en",
+            "en-r",
+            "english-us",
+        ] {
+            assert!(is_listed(&names, name), "Expected {} to be listed", name);
+        }
+        assert!(!is_listed(&names, "fr"));
+        assert!(!is_listed(&names, "m"));
+    }
+
+    #[test]
+    fn options_in_range() {
+        let options = EspeakOptions::new()
+            .speed(450)
+            .and_then(|options| options.pitch(0))
+            .and_then(|options| options.word_gap(5))
+            .unwrap();
+        assert_eq!(options.args(), vec!["-s", "450", "-p", "0", "-g", "5"]);
+        assert!(EspeakOptions::new().args().is_empty());
+
+        match EspeakOptions::new().speed(79) {
+            Err(Error::OptionOutOfRange { option, value, .. }) => {
+                assert_eq!((option, value), ("speed", 79))
+            }
+            other => panic!("Expected speed to be out of range, got: {:?}", other),
+        }
+        assert!(EspeakOptions::new().pitch(100).is_err());
+        assert!(EspeakOptions::new().amplitude(201).is_err());
+        assert!(EspeakOptions::new().word_gap(101).is_err());
+    }
+
+    #[test]
+    fn flavors_and_features() {
+        let ng = EspeakFlavor::from_version(
+            "eSpeak NG text-to-speech: 1.51  Data at: /usr/share/espeak-ng-data",
+        );
+        let classic = EspeakFlavor::from_version(
+            "eSpeak text-to-speech: 1.48.15  16.Apr.15  Data at: /usr/share/espeak-data",
+        );
+        assert_eq!(
+            (ng, classic),
+            (EspeakFlavor::EspeakNg, EspeakFlavor::Espeak)
+        );
+
+        let features = EspeakFeatures::detect(ng, "--ipa      Write phonemes to stdout using IPA");
+        assert!(features.ipa && features.ssml_phonemes);
+        let features = EspeakFeatures::detect(classic, "-x\t   Write phoneme mnemonics to stdout");
+        assert!(!features.ipa && !features.ssml_phonemes);
+    }
+
+    #[test]
+    fn voices_for_locales() {
+        let names = voice_names(VOICES);
+        let voice = |locale| voice_for_locale(locale, &names);
+        assert_eq!(voice("de_AT.UTF-8"), Some("de".to_string()));
+        assert_eq!(voice("en_US.UTF-8"), Some("en-us".to_string()));
+        assert_eq!(voice("af_ZA@euro"), Some("af".to_string()));
+        assert_eq!(voice("C.UTF-8"), None);
+        assert_eq!(voice("POSIX"), None);
+        assert_eq!(voice("fr_FR"), None);
+    }
+
+    #[test]
+    fn override_binary() {
+        let bundled = PathBuf::from("/opt/bin/espeak-ng");
+        assert_eq!(
+            binaries(Some("/tmp/stub".into()), Some(bundled.clone())),
+            vec![PathBuf::from("/tmp/stub")]
+        );
+        assert_eq!(
+            binaries(Some("".into()), Some(bundled.clone())),
+            vec![bundled]
+        );
+        assert_eq!(
+            binaries(None, None),
+            vec![PathBuf::from("espeak-ng"), PathBuf::from("espeak")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stub_binary() {
+        use std::fs;
+        use tempfile::tempdir;
+
+        // Answers like espeak-ng and logs how it was asked to speak
+        const STUB: &str = r#"#!/bin/sh
+case "$*" in
+    *--version*) echo "eSpeak NG text-to-speech: 1.51  Data at: /usr/share/espeak-ng-data" ;;
+    *--help*) echo "--ipa      Write phonemes to stdout using IPA" ;;
+    *--voices*) echo "$*" >> voices.log; echo "Pty Language Age/Gender VoiceName File Other Languages" ;;
+    *" -x"*) cat > /dev/null; printf " h@l'oU\n w'3:ld\n" ;;
+    *) cat > /dev/null; echo "$* $ESPEAK_DATA_PATH $(pwd)" > spoken.log ;;
+esac
+"#;
+        let dir = tempdir().unwrap();
+        let stub = write_stub(dir.path(), STUB);
+
+        let espeak = Espeak::builder()
+            .binary(&stub)
+            .env("ESPEAK_DATA_PATH", "/opt/espeak-data")
+            .data_path("/opt/data")
+            .working_dir(dir.path())
+            .build_with_override(None)
+            .expect("stub not accepted as espeak");
+        assert_eq!(espeak.flavor(), EspeakFlavor::EspeakNg);
+        assert_eq!(espeak.version(), Some(&BackendVersion::new(1, 51, 0)));
+        assert!(espeak.features().ipa);
+        assert!(!dir.path().join("voices.log").exists());
+
+        Voice::speak(&espeak, "Hello")
+            .unwrap()
+            .await_done()
+            .unwrap();
+        let log = fs::read_to_string(dir.path().join("spoken.log")).unwrap();
+        let working_dir = dir.path().canonicalize().unwrap();
+        assert!(log.starts_with("--path=/opt/data -m"), "{}", log);
+        assert!(
+            log.trim_end()
+                .ends_with(&format!("/opt/espeak-data {}", working_dir.display())),
+            "{}",
+            log
+        );
+
+        let transcription = espeak.transcribe("Hello, world", PhoneticAlphabet::Espeak);
+        assert_eq!(transcription.unwrap(), "h@l'oU w'3:ld");
+        // The locale voice is looked up once, when first needed
+        let listed = fs::read_to_string(dir.path().join("voices.log")).unwrap();
+        assert_eq!(listed.lines().count(), 1, "{}", listed);
+        let words = espeak
+            .transcribe_words(&Utterance::parse("Hello, world"), PhoneticAlphabet::Espeak)
+            .unwrap();
+        assert_eq!(words.len(), 2);
+        assert_eq!(words[1].word, "world");
+        assert_eq!(words[1].phonemes, "w'3:ld");
+        assert!(espeak.transcribe("Hello", PhoneticAlphabet::Sapi).is_err());
+        match espeak.transcribe_words(&Utterance::parse("Hello"), PhoneticAlphabet::Espeak) {
+            Err(err @ Error::TranscriptionMismatch { .. }) => assert_eq!(
+                err.to_string(),
+                "espeak printed 2 lines of phonemes for 1 words"
+            ),
+            other => panic!("Expected mismatch, got: {:?}", other),
+        }
+
+        match Espeak::builder()
+            .binary(dir.path().join("missing"))
+            .build_with_override(None)
+        {
+            Err(Error::EspeakNotInstalled(_)) => (),
+            other => panic!("Expected missing binary to fail, got: {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn transcribe_long_text() {
+        use tempfile::tempdir;
+
+        // Echoes its input as the phonemes, filling the stdout pipe
+        // long before all input is read
+        const STUB: &str = r#"#!/bin/sh
+case "$*" in
+    *--version*) echo "eSpeak NG text-to-speech: 1.51  Data at: /usr/share/espeak-ng-data" ;;
+    *" -x"*) cat ;;
+esac
+"#;
+        let dir = tempdir().unwrap();
+        let stub = write_stub(dir.path(), STUB);
+
+        let espeak = Espeak::builder()
+            .binary(&stub)
+            .build_with_override(None)
+            .unwrap();
+        let text = "word ".repeat(100_000);
+        let phonemes = espeak
+            .transcribe(&text, PhoneticAlphabet::Espeak)
+            .expect("long text not transcribed");
+        assert!(phonemes.len() > text.len());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_voice_listing() {
+        use tempfile::tempdir;
+
+        const STUB: &str = r#"#!/bin/sh
+case "$*" in
+    *--version*) echo "eSpeak NG text-to-speech: 1.51  Data at: /usr/share/espeak-ng-data" ;;
+    *--voices*) echo "no voices in /opt/data" >&2; exit 1 ;;
+esac
+"#;
+        let dir = tempdir().unwrap();
+        let stub = write_stub(dir.path(), STUB);
+
+        let espeak = Espeak::builder()
+            .binary(&stub)
+            .build_with_override(None)
+            .unwrap();
+        match espeak.voices() {
+            Err(Error::CannotListVoices { status, stderr, .. }) => {
+                assert_eq!(status.code(), Some(1));
+                assert_eq!(stderr.as_str().trim_end(), "no voices in /opt/data");
+            }
+            other => panic!("Expected listing to fail, got: {:?}", other),
+        }
+    }
+
+    /// Writes an executable shell script to stand in for `espeak`.
+    #[cfg(unix)]
+    fn write_stub(dir: &Path, script: &str) -> PathBuf {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let stub = dir.join("espeak-stub");
+        fs::write(&stub, script).unwrap();
+        fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+        stub
+    }
 }
@@ -40,9 +40,9 @@ pub fn any_voice() -> Result<AnyVoice, Error> {
 /// Tries to initialize an [`Espeak`](struct.Espeak.html)
 /// voice.
 ///
-/// Requires `espeak` and `sh` to be available on the
-/// path. If `paplay` is available, it will be used
-/// for output.
+/// Requires `espeak-ng` or `espeak` and `sh` to be
+/// available on the path. If `paplay` is available, it
+/// will be used for output.
 pub fn espeak() -> Result<Espeak, EspeakError> {
     Espeak::new()
 }
@@ -0,0 +1,606 @@
+//! User pronunciation lexicons that replace words with other
+//! text or phonemes before any backend sees them, loaded from
+//! W3C Pronunciation Lexicon Specification (PLS) documents or
+//! a simple tab separated format.
+
+pub use err::Error;
+
+use crate::phoneme::PhoneticAlphabet;
+use crate::ssml::node_text;
+use crate::token::Token;
+use crate::utterance::Utterance;
+use regex::{Regex, RegexBuilder};
+use roxmltree::{Document, Node};
+use std::borrow::Cow;
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// Words and how to pronounce them, applied to utterances
+/// before speaking.
+///
+/// Entries are applied in the order they were added, so that
+/// text inserted by an earlier alias can be matched again by
+/// later entries:
+///
+/// ```
+/// use tavla::{Lexicon, PhoneticAlphabet, Token, Utterance};
+///
+/// let lexicon = Lexicon::new()
+///     .alias("kubectl", "cube control")
+///     .phonemes("tavla", PhoneticAlphabet::Ipa, "ˈtɑːvlə");
+///
+/// let utterance = lexicon.apply(Utterance::parse("Run Kubectl"));
+/// assert_eq!(utterance.tokens()[1], Token::Normal("cube control".into()));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lexicon {
+    entries: Vec<Entry>,
+}
+
+/// How an entry of a [`Lexicon`](struct.Lexicon.html) finds
+/// the words it applies to.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// A whole word or phrase, ignoring case.
+    Word(String),
+    /// A whole word or phrase with exactly the given case.
+    CaseSensitive(String),
+    /// Any match of the regular expression.
+    Regex(Regex),
+}
+
+/// What a matched word is spoken as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pronunciation {
+    /// Other text, like `cube control` for `kubectl`.
+    Alias(String),
+    /// Phonemes in the given alphabet, used by backends that
+    /// support them, with the matched word as fallback.
+    Phonemes {
+        alphabet: PhoneticAlphabet,
+        phonemes: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    pattern: Pattern,
+    matcher: Regex,
+    pronunciation: Pronunciation,
+}
+
+impl Lexicon {
+    /// Creates an empty lexicon.
+    pub fn new() -> Lexicon {
+        Lexicon::default()
+    }
+
+    /// Parses a PLS document, see
+    /// <https://www.w3.org/TR/pronunciation-lexicon/>.
+    ///
+    /// Graphemes match whole words, ignoring case. Of several
+    /// pronunciations of a lexeme, the first is used.
+    pub fn from_pls(source: &str) -> Result<Lexicon, Error> {
+        let document = Document::parse(source).map_err(Error::malformed)?;
+        let root = document.root_element();
+        let line = |node: Node| document.text_pos_at(node.range().start).row;
+
+        if root.tag_name().name() != "lexicon" {
+            return Err(Error::not_lexicon(line(root), root.tag_name().name()));
+        }
+        let default_alphabet = root.attribute("alphabet").unwrap_or("ipa");
+
+        let mut lexicon = Lexicon::new();
+        for lexeme in root
+            .children()
+            .filter(|node| node.tag_name().name() == "lexeme")
+        {
+            let pronunciation = lexeme
+                .children()
+                .find_map(|node| match node.tag_name().name() {
+                    "alias" => Some(Ok(Pronunciation::Alias(node_text(node)))),
+                    "phoneme" => {
+                        let alphabet = node.attribute("alphabet").unwrap_or(default_alphabet);
+                        Some(phonemes(alphabet, &node_text(node), line(node)))
+                    }
+                    _ => None,
+                })
+                .ok_or_else(|| Error::missing_pronunciation(line(lexeme)))??;
+
+            for grapheme in lexeme
+                .children()
+                .filter(|node| node.tag_name().name() == "grapheme")
+            {
+                lexicon = lexicon.entry(Pattern::Word(node_text(grapheme)), pronunciation.clone());
+            }
+        }
+
+        Ok(lexicon)
+    }
+
+    /// Parses a lexicon with one entry per line, made up of the
+    /// word and its pronunciation, separated by a tab (shown as
+    /// spaces here):
+    ///
+    /// ```text
+    /// # Lines starting with a hash are comments
+    /// kubectl    cube control
+    /// tavla    ipa:ˈtɑːvlə
+    /// SQL    sequel    case
+    /// k8s|kube    kubernetes    regex
+    /// ```
+    ///
+    /// Pronunciations starting with `ipa:`, `espeak:`, `say:` or
+    /// `sapi:` are phonemes, others are aliases. An optional third
+    /// column makes the word match only with the same `case`, or
+    /// turns it into a `regex`.
+    pub fn from_tsv(source: &str) -> Result<Lexicon, Error> {
+        let mut lexicon = Lexicon::new();
+
+        for (idx, line) in source.lines().enumerate() {
+            let line_number = idx as u32 + 1;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let columns: Vec<&str> = line.split('\t').map(str::trim).collect();
+            let (word, pronunciation, kind) = match columns.as_slice() {
+                [word, pronunciation] => (*word, *pronunciation, "word"),
+                [word, pronunciation, kind] => (*word, *pronunciation, *kind),
+                _ => return Err(Error::invalid_line(line_number, line)),
+            };
+            if word.is_empty() || pronunciation.is_empty() {
+                return Err(Error::invalid_line(line_number, line));
+            }
+
+            let pattern = match kind {
+                "word" => Pattern::Word(word.to_string()),
+                "case" => Pattern::CaseSensitive(word.to_string()),
+                "regex" => Pattern::Regex(
+                    Regex::new(word).map_err(|err| Error::invalid_regex(line_number, err))?,
+                ),
+                _ => return Err(Error::invalid_line(line_number, line)),
+            };
+
+            let pronunciation = match pronunciation.split_once(':') {
+                Some((keyword, phonemes_text))
+                    if PhoneticAlphabet::from_keyword(keyword).is_some() =>
+                {
+                    phonemes(keyword, phonemes_text, line_number)?
+                }
+                _ => Pronunciation::Alias(pronunciation.to_string()),
+            };
+
+            lexicon = lexicon.entry(pattern, pronunciation);
+        }
+
+        Ok(lexicon)
+    }
+
+    /// Reads a lexicon from a file, as PLS if it ends in `.pls`
+    /// or `.xml`, otherwise as tab separated values.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Lexicon, Error> {
+        let path = path.as_ref();
+        let source = read_to_string(path).map_err(|err| Error::cannot_read(path, err))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("pls") | Some("xml") => Lexicon::from_pls(&source),
+            _ => Lexicon::from_tsv(&source),
+        }
+    }
+
+    /// Adds an entry that speaks a word as other text,
+    /// ignoring case.
+    pub fn alias<W, A>(self, word: W, alias: A) -> Lexicon
+    where
+        W: Into<String>,
+        A: Into<String>,
+    {
+        self.entry(
+            Pattern::Word(word.into()),
+            Pronunciation::Alias(alias.into()),
+        )
+    }
+
+    /// Adds an entry that pronounces a word with the given
+    /// phonemes, ignoring case.
+    pub fn phonemes<W, P>(self, word: W, alphabet: PhoneticAlphabet, phonemes: P) -> Lexicon
+    where
+        W: Into<String>,
+        P: Into<String>,
+    {
+        self.entry(
+            Pattern::Word(word.into()),
+            Pronunciation::Phonemes {
+                alphabet,
+                phonemes: phonemes.into(),
+            },
+        )
+    }
+
+    /// Adds an entry with any pattern and pronunciation.
+    pub fn entry(mut self, pattern: Pattern, pronunciation: Pronunciation) -> Lexicon {
+        let matcher = match pattern {
+            Pattern::Word(ref word) => word_matcher(word, false),
+            Pattern::CaseSensitive(ref word) => word_matcher(word, true),
+            Pattern::Regex(ref regex) => regex.clone(),
+        };
+        self.entries.push(Entry {
+            pattern,
+            matcher,
+            pronunciation,
+        });
+        self
+    }
+
+    /// Adds the entries of another lexicon after the own ones.
+    pub fn extend(mut self, other: Lexicon) -> Lexicon {
+        self.entries.extend(other.entries);
+        self
+    }
+
+    /// The number of entries, counting every grapheme of an
+    /// entry with alternatives.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if the lexicon has no entries, so that applying it
+    /// changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Replaces matching words in spoken text with their
+    /// pronunciation. Say-as hints and phonemes are kept.
+    pub fn apply(&self, utterance: Utterance) -> Utterance {
+        self.entries.iter().fold(utterance, |utterance, entry| {
+            utterance
+                .into_iter()
+                .flat_map(|token| entry.apply(token))
+                .collect()
+        })
+    }
+}
+
+impl Entry {
+    fn apply(&self, token: Token<'static>) -> Vec<Token<'static>> {
+        let text = match &token {
+            Token::Normal(text)
+            | Token::Emphasised(text)
+            | Token::Strong(text)
+            | Token::Reduced(text) => text.clone(),
+            _ => return vec![token],
+        };
+        let style = |text: Cow<'static, str>| token.clone().map_text(|_| text);
+
+        let mut tokens = Vec::new();
+        let mut rest_start = 0;
+        for found in self.matcher.find_iter(&text).filter(|m| !m.is_empty()) {
+            let before = text[rest_start..found.start()].trim();
+            if !before.is_empty() {
+                tokens.push(style(before.to_string().into()));
+            }
+            tokens.push(match self.pronunciation {
+                Pronunciation::Alias(ref alias) => style(alias.clone().into()),
+                Pronunciation::Phonemes {
+                    alphabet,
+                    ref phonemes,
+                } => Token::Phoneme {
+                    alphabet,
+                    phonemes: phonemes.clone().into(),
+                    text: found.as_str().to_string().into(),
+                },
+            });
+            rest_start = found.end();
+        }
+
+        if rest_start == 0 {
+            return vec![style(text)];
+        }
+        let rest = text[rest_start..].trim();
+        if !rest.is_empty() {
+            tokens.push(style(rest.to_string().into()));
+        }
+        tokens
+    }
+}
+
+// The matcher is made from the pattern
+impl PartialEq for Entry {
+    fn eq(&self, other: &Entry) -> bool {
+        self.pattern == other.pattern && self.pronunciation == other.pronunciation
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Pattern) -> bool {
+        match (self, other) {
+            (Pattern::Word(a), Pattern::Word(b))
+            | (Pattern::CaseSensitive(a), Pattern::CaseSensitive(b)) => a == b,
+            (Pattern::Regex(a), Pattern::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Pattern {}
+
+/// Matches the word as a whole, also if it starts or ends with
+/// characters that are not part of words, like `C++`.
+fn word_matcher(word: &str, case_sensitive: bool) -> Regex {
+    let is_word_char = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let boundary = |c| if is_word_char(c) { r"\b" } else { "" };
+    let pattern = format!(
+        "{}{}{}",
+        boundary(word.chars().next()),
+        regex::escape(word),
+        boundary(word.chars().next_back())
+    );
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .expect("escaped words are valid regular expressions")
+}
+
+/// Validates phonemes in the alphabet with the given name, like
+/// `ipa` or `x-espeak`, found on the given line.
+fn phonemes(alphabet: &str, phonemes: &str, line: u32) -> Result<Pronunciation, Error> {
+    let keyword = alphabet.trim_start_matches("x-");
+    let alphabet = PhoneticAlphabet::from_keyword(keyword)
+        .ok_or_else(|| Error::unsupported_alphabet(line, alphabet))?;
+    let phonemes = phonemes.trim();
+
+    alphabet
+        .validate(phonemes)
+        .map_err(|err| Error::invalid_phonemes(line, err))?;
+    Ok(Pronunciation::Phonemes {
+        alphabet,
+        phonemes: phonemes.to_string(),
+    })
+}
+
+mod err {
+    use crate::phoneme::Error as PhonemeError;
+    use failure::{Backtrace, Fail};
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    /// A lexicon that could not be read or parsed.
+    ///
+    /// Lines start at one.
+    #[derive(Fail, Debug)]
+    pub enum Error {
+        #[fail(display = "could not read lexicon {:?}: {}", path, cause)]
+        CannotRead {
+            path: PathBuf,
+            #[cause]
+            cause: io::Error,
+            backtrace: Backtrace,
+        },
+        #[fail(display = "malformed PLS lexicon: {}", cause)]
+        Malformed {
+            line: u32,
+            #[cause]
+            cause: roxmltree::Error,
+            backtrace: Backtrace,
+        },
+        #[fail(
+            display = "PLS root element must be <lexicon>, but found <{}> on line {}",
+            element, line
+        )]
+        NotLexicon {
+            line: u32,
+            element: String,
+            backtrace: Backtrace,
+        },
+        #[fail(display = "lexeme on line {} has neither phoneme nor alias", line)]
+        MissingPronunciation { line: u32, backtrace: Backtrace },
+        #[fail(
+            display = "unsupported phonetic alphabet {:?} on line {}",
+            alphabet, line
+        )]
+        UnsupportedAlphabet {
+            line: u32,
+            alphabet: String,
+            backtrace: Backtrace,
+        },
+        #[fail(display = "invalid phonemes on line {}: {}", line, cause)]
+        InvalidPhonemes {
+            line: u32,
+            #[cause]
+            cause: PhonemeError,
+        },
+        #[fail(display = "invalid regular expression on line {}: {}", line, cause)]
+        InvalidRegex {
+            line: u32,
+            #[cause]
+            cause: regex::Error,
+            backtrace: Backtrace,
+        },
+        #[fail(
+            display = "expected word, pronunciation and optionally case or regex separated by tabs on line {}, but found {:?}",
+            line, content
+        )]
+        InvalidLine {
+            line: u32,
+            content: String,
+            backtrace: Backtrace,
+        },
+    }
+
+    impl Error {
+        pub fn cannot_read(path: &Path, cause: io::Error) -> Self {
+            Error::CannotRead {
+                path: path.into(),
+                cause,
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn malformed(cause: roxmltree::Error) -> Self {
+            Error::Malformed {
+                line: cause.pos().row,
+                cause,
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn not_lexicon(line: u32, element: &str) -> Self {
+            Error::NotLexicon {
+                line,
+                element: element.into(),
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn missing_pronunciation(line: u32) -> Self {
+            Error::MissingPronunciation {
+                line,
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn unsupported_alphabet(line: u32, alphabet: &str) -> Self {
+            Error::UnsupportedAlphabet {
+                line,
+                alphabet: alphabet.into(),
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn invalid_phonemes(line: u32, cause: PhonemeError) -> Self {
+            Error::InvalidPhonemes { line, cause }
+        }
+
+        pub fn invalid_regex(line: u32, cause: regex::Error) -> Self {
+            Error::InvalidRegex {
+                line,
+                cause,
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn invalid_line(line: u32, content: &str) -> Self {
+            Error::InvalidLine {
+                line,
+                content: content.into(),
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        /// The line the problem was found on, if any.
+        pub fn line(&self) -> Option<u32> {
+            match self {
+                Error::CannotRead { .. } => None,
+                Error::Malformed { line, .. }
+                | Error::NotLexicon { line, .. }
+                | Error::MissingPronunciation { line, .. }
+                | Error::UnsupportedAlphabet { line, .. }
+                | Error::InvalidPhonemes { line, .. }
+                | Error::InvalidRegex { line, .. }
+                | Error::InvalidLine { line, .. } => Some(*line),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PLS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<lexicon version="1.0" xmlns="http://www.w3.org/2005/01/pronunciation-lexicon"
+    alphabet="ipa" xml:lang="en-US">
+  <lexeme>
+    <grapheme>tavla</grapheme>
+    <phoneme>ˈtɑːvlə</phoneme>
+  </lexeme>
+  <lexeme>
+    <grapheme>W3C</grapheme>
+    <grapheme>W3 C</grapheme>
+    <alias>World Wide Web Consortium</alias>
+  </lexeme>
+</lexicon>"#;
+
+    #[test]
+    fn apply_pls() {
+        let lexicon = Lexicon::from_pls(PLS).unwrap();
+        assert_eq!(lexicon.len(), 3);
+
+        let utterance = lexicon.apply(Utterance::parse("_Tavla_ follows the w3c."));
+        assert_eq!(
+            utterance.tokens(),
+            &[
+                Token::Phoneme {
+                    alphabet: PhoneticAlphabet::Ipa,
+                    phonemes: "ˈtɑːvlə".into(),
+                    text: "Tavla".into(),
+                },
+                Token::Normal("follows the".into()),
+                Token::Normal("World Wide Web Consortium".into()),
+                Token::Pause(crate::PauseDuration::Sentence),
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_tsv() {
+        let lexicon = Lexicon::from_tsv(
+            "# Product names\n\
+             SQL\tsequel\tcase\n\
+             k8s|kube\tkubernetes\tregex\n\
+             nginx\tespeak:'EndZinEks\n",
+        )
+        .unwrap();
+
+        let utterance = lexicon.apply(Utterance::parse("sql and SQL on k8s, Nginx"));
+        assert_eq!(
+            utterance.tokens(),
+            &[
+                Token::Normal("sql and".into()),
+                Token::Normal("sequel".into()),
+                Token::Normal("on".into()),
+                Token::Normal("kubernetes".into()),
+                Token::Normal(",".into()),
+                Token::Phoneme {
+                    alphabet: PhoneticAlphabet::Espeak,
+                    phonemes: "'EndZinEks".into(),
+                    text: "Nginx".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn words_are_matched_whole() {
+        let lexicon = Lexicon::new()
+            .alias("db", "database")
+            .alias("C++", "C plus plus");
+        let utterance = lexicon.apply(Utterance::parse("dbx in db with C++"));
+        assert_eq!(
+            utterance.tokens(),
+            &[
+                Token::Normal("dbx in".into()),
+                Token::Normal("database".into()),
+                Token::Normal("with".into()),
+                Token::Normal("C plus plus".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_have_lines() {
+        let invalid_phonemes = Lexicon::from_tsv("ok\tfine\n\nbad\tipa:TAVLA").unwrap_err();
+        assert_eq!(invalid_phonemes.line(), Some(3));
+
+        let missing_column = Lexicon::from_tsv("word").unwrap_err();
+        assert_eq!(missing_column.line(), Some(1));
+
+        let missing_pronunciation =
+            Lexicon::from_pls("<lexicon>\n<lexeme><grapheme>x</grapheme></lexeme></lexicon>")
+                .unwrap_err();
+        assert_eq!(missing_pronunciation.line(), Some(2));
+    }
+}
@@ -31,22 +31,45 @@
 //! # }
 //! ```
 
+// The derive macros of failure generate impl blocks inside of constants
+#![allow(non_local_definitions)]
+
 extern crate failure;
+extern crate pulldown_cmark;
+extern crate regex;
+extern crate roxmltree;
 #[cfg(test)]
 extern crate tempfile;
 
 mod any;
 mod child;
+mod chunked;
 #[cfg(target_os = "windows")]
 mod cscript;
 mod espeak;
 mod factory;
+mod lexicon;
+mod markdown;
+mod normalize;
+mod phoneme;
 mod prelude;
+mod preset;
+mod render;
+mod sanitize;
+mod say_as;
 #[cfg(target_os = "macos")]
 mod say;
+mod segment;
+mod settings;
 mod speech;
+mod ssml;
+mod symbols;
 mod token;
+mod transcribe;
+mod utterance;
+mod validate;
 mod version;
 mod voice;
+mod voices;
 
 pub use prelude::*;
@@ -1,26 +1,51 @@
+// The derive macros of failure generate impl blocks inside of constants
+#![allow(non_local_definitions)]
+
 extern crate clap;
 extern crate failure;
+extern crate pulldown_cmark;
+extern crate regex;
+extern crate roxmltree;
 
 mod any;
 mod child;
+mod chunked;
 #[cfg(target_os = "windows")]
 mod cscript;
 mod espeak;
 mod factory;
+mod lexicon;
+mod markdown;
+mod normalize;
+mod phoneme;
 mod prelude;
+mod preset;
+mod render;
+mod sanitize;
+mod say_as;
 #[cfg(target_os = "macos")]
 mod say;
+mod segment;
+mod settings;
 mod speech;
+mod ssml;
+mod symbols;
 mod token;
+mod transcribe;
+mod utterance;
+mod validate;
 mod version;
 mod voice;
+mod voices;
 
 pub use prelude::*;
 
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches, SubCommand};
 use failure::bail;
+use std::fs;
 use std::io::{stdin, BufRead, Read};
 use std::path::Path;
+use std::process;
 
 fn main() -> Result<(), failure::Error> {
     let args = App::new("tavla")
@@ -40,22 +65,146 @@ fn main() -> Result<(), failure::Error> {
                 .help("Write a WAV file to the specified path instead of speaking out loud")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("markup")
+                .short("m")
+                .long("markup")
+                .help("How the input is marked up")
+                .takes_value(true)
+                .possible_values(&["tavla", "ssml", "markdown"])
+                .default_value("tavla"),
+        )
+        .arg(
+            Arg::with_name("lines")
+                .long("lines")
+                .help("How line breaks are read, prose joins hard-wrapped lines")
+                .takes_value(true)
+                .possible_values(&["split", "prose", "verse", "list"])
+                .default_value("split"),
+        )
+        .arg(
+            Arg::with_name("preset")
+                .short("p")
+                .long("preset")
+                .help("Speak in the given way on any backend")
+                .takes_value(true)
+                .possible_values(&["calm", "urgent", "fast-reader"]),
+        )
+        .arg(
+            Arg::with_name("normalize")
+                .short("n")
+                .long("normalize")
+                .help("Expand numbers, dates and abbreviations into words of the given language")
+                .takes_value(true)
+                .possible_values(&["en", "de"]),
+        )
+        .arg(
+            Arg::with_name("symbols")
+                .short("s")
+                .long("symbols")
+                .help("Whether emoji and symbols are kept, removed or spoken by name")
+                .takes_value(true)
+                .possible_values(&["keep", "remove", "names"])
+                .default_value("keep"),
+        )
+        .arg(
+            Arg::with_name("lexicon")
+                .short("l")
+                .long("lexicon")
+                .help("Pronounce words as listed in a PLS or tab separated lexicon file")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
         .arg(
             Arg::with_name("INPUT")
                 .help("Other args are spoken aloud")
                 .multiple(true)
                 .takes_value(true),
         )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Checks tavla markup for likely mistakes and reports them")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("Files to check, stdin is checked if none are given")
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("phonemes")
+                .about("Prints the phonemes that espeak would speak, without speaking")
+                .arg(
+                    Arg::with_name("ipa")
+                        .long("ipa")
+                        .help("Print IPA instead of the phoneme mnemonics of espeak"),
+                )
+                .arg(
+                    Arg::with_name("words")
+                        .short("w")
+                        .long("words")
+                        .help("Print every word with its phonemes on a line of its own"),
+                )
+                .arg(
+                    Arg::with_name("voice")
+                        .short("v")
+                        .long("voice")
+                        .help("Transcribe with the given espeak voice, like de or en-us+f3")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("lexicon")
+                        .short("l")
+                        .long("lexicon")
+                        .help("Pronounce words as listed in a PLS or tab separated lexicon file")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("Text to transcribe, stdin is read if none is given")
+                        .multiple(true),
+                ),
+        )
         .get_matches();
 
-    let voice = any_voice()?;
+    if let Some(check_args) = args.subcommand_matches("check") {
+        return check(check_args);
+    }
+    if let Some(phonemes_args) = args.subcommand_matches("phonemes") {
+        return phonemes(phonemes_args);
+    }
+
+    let mut voice = any_voice()?;
+    if let Some(preset) = args.value_of("preset").and_then(Preset::from_name) {
+        voice = voice.with_preset(preset);
+    }
+    voice.settings_mut().normalization = args.value_of("normalize").and_then(Language::from_tag);
+    voice.settings_mut().symbols = match args.value_of("symbols") {
+        Some("remove") => SymbolVerbosity::Remove,
+        Some("names") => SymbolVerbosity::Names,
+        _ => SymbolVerbosity::Keep,
+    };
+    for path in args.values_of("lexicon").into_iter().flatten() {
+        voice.settings_mut().lexicons.push(Lexicon::load(path)?);
+    }
     let target_file = args.value_of("file").map(Path::new);
+    let markup = args.value_of("markup").unwrap_or("tavla");
+    let line_mode = match args.value_of("lines") {
+        Some("prose") => LineMode::Prose,
+        Some("verse") => LineMode::Verse,
+        Some("list") => LineMode::List,
+        _ => LineMode::Split,
+    };
 
     if args.is_present("stdin") {
-        if let Some(target_file) = target_file {
+        // Only tavla markup can be spoken line by line, documents
+        // and lines read in other modes need to be complete first
+        if target_file.is_some() || markup != "tavla" || line_mode != LineMode::Split {
             let mut text = String::new();
             stdin().lock().read_to_string(&mut text)?;
-            voice.speak_to_file(text, target_file)?.await_done()?;
+            speak(&voice, &text, markup, line_mode, target_file)?.await_done()?;
         } else {
             for line in stdin().lock().lines() {
                 voice.speak(line?)?.await_done()?;
@@ -65,11 +214,7 @@ fn main() -> Result<(), failure::Error> {
         match args.values_of("INPUT") {
             Some(input_args) => {
                 let text = join(input_args);
-                match target_file {
-                    None => voice.speak(text),
-                    Some(target_file) => voice.speak_to_file(text, target_file),
-                }?
-                .await_done()?
+                speak(&voice, &text, markup, line_mode, target_file)?.await_done()?
             }
             None => bail!("No command line arguments for speech specified"),
         }
@@ -78,6 +223,100 @@ fn main() -> Result<(), failure::Error> {
     Ok(())
 }
 
+fn speak(
+    voice: &AnyVoice,
+    text: &str,
+    markup: &str,
+    line_mode: LineMode,
+    target_file: Option<&Path>,
+) -> Result<AnySpeech, failure::Error> {
+    let speech = match (markup, target_file) {
+        ("ssml", None) => voice.speak_ssml(text),
+        ("ssml", Some(target_file)) => voice.speak_ssml_to_file(text, target_file),
+        ("markdown", None) => voice.speak_markdown(text),
+        ("markdown", Some(target_file)) => voice.speak_markdown_to_file(text, target_file),
+        (_, None) => voice.speak_utterance(&parse(text, line_mode)),
+        (_, Some(target_file)) => {
+            voice.speak_utterance_to_file(&parse(text, line_mode), target_file)
+        }
+    }?;
+    Ok(speech)
+}
+
+fn parse(markup: &str, line_mode: LineMode) -> Utterance {
+    Tokenizer::new(markup).with_line_mode(line_mode).collect()
+}
+
+/// Prints diagnostics for the files in the args, or stdin, and
+/// exits with an error status if any of them has errors.
+fn check(args: &ArgMatches) -> Result<(), failure::Error> {
+    let sources = match args.values_of("FILE") {
+        Some(paths) => paths
+            .map(|path| Ok((path.to_string(), fs::read_to_string(path)?)))
+            .collect::<Result<Vec<_>, failure::Error>>()?,
+        None => {
+            let mut text = String::new();
+            stdin().lock().read_to_string(&mut text)?;
+            vec![("<stdin>".to_string(), text)]
+        }
+    };
+
+    let mut errors = 0;
+    let mut warnings = 0;
+    for (name, source) in &sources {
+        for diagnostic in validate(source) {
+            match diagnostic.severity() {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+            eprintln!("{}", diagnostic.report(name, source));
+        }
+    }
+
+    if errors + warnings > 0 {
+        eprintln!("{} errors, {} warnings", errors, warnings);
+    }
+    if errors > 0 {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// Prints the phonemes of the text in the args, or stdin, as
+/// transcribed by espeak with the lexicons in the args.
+fn phonemes(args: &ArgMatches) -> Result<(), failure::Error> {
+    let mut espeak = Espeak::new()?;
+    if let Some(voice) = args.value_of("voice") {
+        espeak = espeak.with_voice(voice, None)?;
+    }
+    for path in args.values_of("lexicon").into_iter().flatten() {
+        espeak.settings_mut().lexicons.push(Lexicon::load(path)?);
+    }
+
+    let text = match args.values_of("INPUT") {
+        Some(input_args) => join(input_args),
+        None => {
+            let mut text = String::new();
+            stdin().lock().read_to_string(&mut text)?;
+            text
+        }
+    };
+    let alphabet = if args.is_present("ipa") {
+        PhoneticAlphabet::Ipa
+    } else {
+        PhoneticAlphabet::Espeak
+    };
+
+    if args.is_present("words") {
+        for transcription in espeak.transcribe_words(&Utterance::parse(&text), alphabet)? {
+            println!("{}\t{}", transcription.word, transcription.phonemes);
+        }
+    } else {
+        println!("{}", espeak.transcribe(&text, alphabet)?);
+    }
+    Ok(())
+}
+
 fn join<'a, I>(iterator: I) -> String
 where
     I: IntoIterator<Item = &'a str>,
@@ -0,0 +1,197 @@
+//! Translation of Markdown documents into tokens.
+//!
+//! Headings and paragraphs end with a paragraph pause and
+//! list items with a sentence pause, lists with a paragraph
+//! pause. Table cells end with a short pause, rows with a
+//! sentence pause. `*em*` is emphasised and `**strong**`
+//! strongly emphasised. Link targets, fenced and indented code
+//! blocks as well as raw HTML are dropped. Images are replaced
+//! with their alt text.
+
+use crate::token::{PauseDuration, Token};
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use std::borrow::Cow;
+
+/// Parses the given Markdown, translating it into tokens.
+pub fn parse(source: &str) -> Vec<Token<'static>> {
+    let mut translation = Translation::default();
+
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    for event in Parser::new_ext(source, options) {
+        translation.event(event);
+    }
+    translation.flush();
+
+    // Unlike the tokenizer, the document end is not a pause
+    if let Some(Token::Pause(_)) = translation.tokens.last() {
+        translation.tokens.pop();
+    }
+
+    translation.tokens
+}
+
+#[derive(Default)]
+struct Translation {
+    tokens: Vec<Token<'static>>,
+    /// Text not yet turned into a token, since the next event
+    /// might continue it with the same emphasis.
+    text: String,
+    /// Makes text tokens for the open emphasis tags, innermost last.
+    emphasis: Vec<fn(Cow<'static, str>) -> Token<'static>>,
+    /// Greater than zero while inside of a code block or other
+    /// content that is not spoken.
+    skip_depth: usize,
+}
+
+impl Translation {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Start(Tag::HtmlBlock) => self.skip_depth += 1,
+            Event::End(TagEnd::CodeBlock) | Event::End(TagEnd::HtmlBlock) => self.skip_depth -= 1,
+            _ if self.skip_depth > 0 => (),
+            Event::Start(Tag::Emphasis) => {
+                self.flush();
+                self.emphasis.push(Token::Emphasised);
+            }
+            Event::Start(Tag::Strong) => {
+                self.flush();
+                self.emphasis.push(Token::Strong);
+            }
+            Event::End(TagEnd::Emphasis) | Event::End(TagEnd::Strong) => {
+                self.flush();
+                self.emphasis.pop();
+            }
+            Event::End(TagEnd::Heading(_))
+            | Event::End(TagEnd::Paragraph)
+            | Event::End(TagEnd::List(_))
+            | Event::End(TagEnd::Table)
+            | Event::Rule => self.end_with(PauseDuration::Paragraph),
+            Event::End(TagEnd::Item)
+            | Event::End(TagEnd::TableHead)
+            | Event::End(TagEnd::TableRow)
+            | Event::HardBreak => self.end_with(PauseDuration::Sentence),
+            Event::End(TagEnd::TableCell) => self.end_with(PauseDuration::Line),
+            Event::Text(text) | Event::Code(text) | Event::InlineMath(text) => {
+                self.push_text(&text)
+            }
+            Event::SoftBreak => self.push_text(" "),
+            // Links keep their text, images their alt text, other tags
+            // only structure their content.
+            _ => (),
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    /// Turns buffered text into a token.
+    fn flush(&mut self) {
+        let text = self.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.text.clear();
+
+        if !text.is_empty() {
+            let text = Cow::Owned(text);
+            let token = self.emphasis.last().copied().unwrap_or(Token::Normal);
+            self.tokens.push(token(text));
+        }
+    }
+
+    /// Ends a table cell, sentence or paragraph, unless directly
+    /// following a pause that is at least as long.
+    fn end_with(&mut self, pause: PauseDuration) {
+        self.flush();
+        match (self.tokens.last(), &pause) {
+            (
+                Some(Token::Pause(PauseDuration::Line)),
+                PauseDuration::Sentence | PauseDuration::Paragraph,
+            )
+            | (Some(Token::Pause(PauseDuration::Sentence)), PauseDuration::Paragraph) => {
+                self.tokens.pop();
+            }
+            (Some(Token::Pause(_)), _) | (None, _) => return,
+            _ => (),
+        }
+        self.tokens.push(Token::Pause(pause));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn headings_paragraphs_and_emphasis() {
+        let tokens = parse(
+            "# Release *0.2*\n\nThis release is **much**\nfaster than `0.1`.\n\n---\n\nEnjoy!",
+        );
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Normal("Release".into()),
+                Token::Emphasised("0.2".into()),
+                Token::Pause(PauseDuration::Paragraph),
+                Token::Normal("This release is".into()),
+                Token::Strong("much".into()),
+                Token::Normal("faster than 0.1.".into()),
+                Token::Pause(PauseDuration::Paragraph),
+                Token::Normal("Enjoy!".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_and_links() {
+        let tokens = parse(
+            "Steps:\n\n1. Open the [dashboard](https://example.com/x)\n2. Restart it\n\nDone",
+        );
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Normal("Steps:".into()),
+                Token::Pause(PauseDuration::Paragraph),
+                Token::Normal("Open the dashboard".into()),
+                Token::Pause(PauseDuration::Sentence),
+                Token::Normal("Restart it".into()),
+                Token::Pause(PauseDuration::Paragraph),
+                Token::Normal("Done".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn table_cells() {
+        let tokens = parse("| Host | Load |\n|------|------|\n| db1 | *high* |\n| db2 | |\n\nOk");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Normal("Host".into()),
+                Token::Pause(PauseDuration::Line),
+                Token::Normal("Load".into()),
+                Token::Pause(PauseDuration::Sentence),
+                Token::Normal("db1".into()),
+                Token::Pause(PauseDuration::Line),
+                Token::Emphasised("high".into()),
+                Token::Pause(PauseDuration::Sentence),
+                Token::Normal("db2".into()),
+                Token::Pause(PauseDuration::Paragraph),
+                Token::Normal("Ok".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn code_blocks_html_and_images() {
+        let tokens = parse(
+            "Run this:\n\n```sh\nrm -rf /tmp/cache\n```\n\n<div>raw</div>\n\n![a red button](button.png)",
+        );
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Normal("Run this:".into()),
+                Token::Pause(PauseDuration::Paragraph),
+                Token::Normal("a red button".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,963 @@
+//! Expansion of numbers, currencies, units, ordinals, dates,
+//! times and common abbreviations into words, so that every
+//! backend reads them the same way instead of guessing.
+//!
+//! Numbers that are part of something else, like versions
+//! (`1.2.3`), identifiers (`db1`, `INC-4711`) or ranges, are
+//! left alone.
+
+use crate::token::Token;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+/// A language with rules for [`normalize`](fn.normalize.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+}
+
+impl Language {
+    /// Picks the language for a tag like `en`, `de-AT` or a
+    /// locale like `de_DE.UTF-8`.
+    pub fn from_tag(tag: &str) -> Option<Language> {
+        let primary = tag.split(['-', '_', '.']).next()?.to_ascii_lowercase();
+        match primary.as_str() {
+            "en" => Some(Language::English),
+            "de" => Some(Language::German),
+            _ => None,
+        }
+    }
+
+    fn rules(self) -> &'static Rules {
+        match self {
+            Language::English => &ENGLISH,
+            Language::German => &GERMAN,
+        }
+    }
+}
+
+/// Expands numbers, currencies, units, ordinals, dates, times
+/// and abbreviations in the text into words of the given
+/// language. Borrows if nothing had to change.
+pub fn normalize(text: &str, language: Language) -> Cow<'_, str> {
+    let rules = language.rules();
+    rules
+        .pattern()
+        .replace_all(text, |captures: &Captures| rules.expand(text, captures))
+}
+
+/// Normalizes the text of tokens that are read as written,
+/// leaving say-as hints and phonemes as they are.
+pub fn normalize_token(token: Token<'_>, language: Language) -> Token<'_> {
+    token.map_text(|text| match normalize(&text, language) {
+        Cow::Borrowed(_) => text,
+        Cow::Owned(normalized) => Cow::Owned(normalized),
+    })
+}
+
+/// A unit after a number, like the `GB` in `3.5 GB`.
+struct Unit {
+    symbol: &'static str,
+    /// Spoken for exactly one, including the number.
+    one: &'static str,
+    /// Spoken after any other number.
+    many: &'static str,
+}
+
+/// A currency before or after an amount, like `€1,200.50`.
+struct Currency {
+    symbols: &'static [&'static str],
+    one: &'static str,
+    many: &'static str,
+    minor_one: &'static str,
+    minor_many: &'static str,
+}
+
+struct Rules {
+    /// Matches a number with optional thousands separators and
+    /// decimals, in the notation of the language.
+    number: &'static str,
+    thousands_separator: char,
+    decimal_separator: char,
+    decimal_word: &'static str,
+    minus: &'static str,
+    and: &'static str,
+    months: [&'static str; 12],
+    abbreviations: &'static [(&'static str, &'static str)],
+    units: &'static [Unit],
+    currencies: &'static [Currency],
+    cardinal: fn(u64) -> String,
+    ordinal: fn(u64) -> String,
+    year: fn(u64) -> String,
+    /// Joins month, ordinal day and optional year into a date.
+    date: fn(&str, String, Option<String>) -> String,
+    /// Reads hours and minutes of a time of day.
+    time: fn(u64, u64) -> String,
+    pattern: OnceLock<Regex>,
+}
+
+impl Rules {
+    fn pattern(&self) -> &Regex {
+        self.pattern.get_or_init(|| {
+            let alternation = |words: &mut Vec<&str>| {
+                // Longest first, so that `GB` is not matched as `G`
+                words.sort_by_key(|word| std::cmp::Reverse(word.len()));
+                words
+                    .iter()
+                    .map(|word| regex::escape(word))
+                    .collect::<Vec<_>>()
+                    .join("|")
+            };
+            let abbreviations = alternation(&mut self.abbreviations.iter().map(|a| a.0).collect());
+            // Single letters only count as units after a space, so
+            // that `1990s` is not read as seconds
+            let (mut letter_units, mut units): (Vec<_>, Vec<_>) =
+                self.units.iter().map(|u| u.symbol).partition(|symbol| {
+                    symbol.len() == 1 && symbol.chars().all(char::is_alphabetic)
+                });
+            let letter_units = alternation(&mut letter_units);
+            let units = alternation(&mut units);
+            let currencies = alternation(
+                &mut self
+                    .currencies
+                    .iter()
+                    .flat_map(|c| c.symbols.iter().cloned())
+                    .collect(),
+            );
+            let months = self.months.join("|");
+            let number = self.number;
+
+            let pattern = format!(
+                r"(?x)
+                (?P<abbreviation>\b(?:{abbreviations}))
+                | (?P<iso_year>\d{{4}})-(?P<iso_month>\d{{2}})-(?P<iso_day>\d{{2}})
+                | (?P<dotted_day>\d{{1,2}})\.(?P<dotted_month>\d{{1,2}})\.(?P<dotted_year>\d{{4}})
+                | (?P<day>\d{{1,2}})\.\s(?P<month>{months})(?:\s(?P<year>\d{{4}}))?
+                | (?P<hours>\d{{1,2}}):(?P<minutes>\d{{2}})(?::\d{{2}})?
+                | (?P<currency_before>{currencies})\s?(?P<amount_after>{number})
+                | (?P<amount_before>{number})\s?(?P<currency_after>{currencies})
+                | (?P<ordinal>\d+)(?:st|nd|rd|th)
+                | (?P<value>{number})(?:\s?(?P<unit>{units})|\s(?P<letter_unit>{letter_units}))
+                | (?P<minus>-)?(?P<number>{number})"
+            );
+            Regex::new(&pattern).expect("normalization rules must be valid regular expressions")
+        })
+    }
+
+    /// Expands a match, or returns it unchanged if it is part
+    /// of something else like a version or identifier.
+    fn expand(&self, text: &str, captures: &Captures) -> String {
+        let whole = captures.get(0).expect("group 0 always matches");
+        let unchanged = whole.as_str().to_string();
+
+        if !is_boundary_before(text[..whole.start()].chars().next_back()) {
+            return unchanged;
+        }
+        let fits_after = is_boundary_after(&text[whole.end()..]);
+
+        if let Some(abbreviation) = captures.name("abbreviation") {
+            return self
+                .abbreviations
+                .iter()
+                .find(|a| a.0 == abbreviation.as_str())
+                .map_or(unchanged, |a| a.1.to_string());
+        }
+
+        // Numbers that continue after a unit or currency are still
+        // expanded, the rest is kept as is
+        let unit = captures
+            .name("unit")
+            .or_else(|| captures.name("letter_unit"));
+        if let (Some(value), Some(unit)) = (captures.name("value"), unit) {
+            return match self.number_words(value.as_str()) {
+                Some(words) if !fits_after => format!("{}{}", words, &unchanged[value.len()..]),
+                Some(words) => self.unit_words(value.as_str(), words, unit.as_str()),
+                None => unchanged,
+            };
+        }
+
+        if !fits_after {
+            return unchanged;
+        }
+
+        let number = |name: &str| captures.name(name).and_then(|m| m.as_str().parse().ok());
+        let expanded = if let (Some(year), Some(month), Some(day)) =
+            (number("iso_year"), number("iso_month"), number("iso_day"))
+        {
+            self.date_words(day, month, Some(year))
+        } else if let (Some(day), Some(month), Some(year)) = (
+            number("dotted_day"),
+            number("dotted_month"),
+            number("dotted_year"),
+        ) {
+            self.date_words(day, month, Some(year))
+        } else if let (Some(day), Some(month)) = (number("day"), captures.name("month")) {
+            let month = self.months.iter().position(|m| *m == month.as_str());
+            month.and_then(|month| self.date_words(day, month as u64 + 1, number("year")))
+        } else if let (Some(hours), Some(minutes)) = (number("hours"), number("minutes")) {
+            Some((self.time)(hours, minutes)).filter(|_| hours < 24 && minutes < 60)
+        } else if let (Some(currency), Some(amount)) = (
+            captures.name("currency_before"),
+            captures.name("amount_after"),
+        ) {
+            self.currency_words(amount.as_str(), currency.as_str())
+        } else if let (Some(amount), Some(currency)) = (
+            captures.name("amount_before"),
+            captures.name("currency_after"),
+        ) {
+            self.currency_words(amount.as_str(), currency.as_str())
+        } else if let Some(ordinal) = number("ordinal") {
+            Some((self.ordinal)(ordinal))
+        } else if let Some(number) = captures.name("number") {
+            self.number_words(number.as_str()).map(|words| {
+                if captures.name("minus").is_some() {
+                    format!("{} {}", self.minus, words)
+                } else {
+                    words
+                }
+            })
+        } else {
+            None
+        };
+
+        expanded.unwrap_or(unchanged)
+    }
+
+    /// Splits a number into its integer part without separators
+    /// and its decimal digits, if any.
+    fn split_number<'n>(&self, number: &'n str) -> Option<(u64, Option<&'n str>)> {
+        let mut parts = number.splitn(2, self.decimal_separator);
+        let integer = parts.next()?.replace(self.thousands_separator, "");
+        Some((integer.parse().ok()?, parts.next()))
+    }
+
+    fn number_words(&self, number: &str) -> Option<String> {
+        let (integer, decimals) = self.split_number(number)?;
+
+        // Leading zeros are not part of the value, like in 007
+        let words = if number.len() > 1 && number.starts_with('0') && decimals.is_none() {
+            self.digit_words(number)
+        } else {
+            (self.cardinal)(integer)
+        };
+
+        Some(match decimals {
+            Some(decimals) => format!(
+                "{} {} {}",
+                words,
+                self.decimal_word,
+                self.digit_words(decimals)
+            ),
+            None => words,
+        })
+    }
+
+    fn digit_words(&self, digits: &str) -> String {
+        digits
+            .chars()
+            .filter_map(|digit| digit.to_digit(10))
+            .map(|digit| (self.cardinal)(u64::from(digit)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn unit_words(&self, value: &str, words: String, symbol: &str) -> String {
+        let unit = self
+            .units
+            .iter()
+            .find(|unit| unit.symbol == symbol)
+            .expect("only known units are matched");
+        if value == "1" {
+            unit.one.to_string()
+        } else {
+            format!("{} {}", words, unit.many)
+        }
+    }
+
+    fn currency_words(&self, amount: &str, symbol: &str) -> Option<String> {
+        let currency = self
+            .currencies
+            .iter()
+            .find(|currency| currency.symbols.contains(&symbol))?;
+        let (major, decimals) = self.split_number(amount)?;
+
+        let minor = match decimals {
+            None => 0,
+            Some(decimals) if decimals.len() <= 2 => format!("{:0<2}", decimals).parse().ok()?,
+            // More precision than cents, read as a decimal number
+            Some(_) => return Some(format!("{} {}", self.number_words(amount)?, currency.many)),
+        };
+
+        let major_words = match major {
+            1 => currency.one.to_string(),
+            major => format!("{} {}", (self.cardinal)(major), currency.many),
+        };
+        let minor_words = match minor {
+            1 => currency.minor_one.to_string(),
+            minor => format!("{} {}", (self.cardinal)(minor), currency.minor_many),
+        };
+
+        Some(match (major, minor) {
+            (0, 0) | (_, 0) => major_words,
+            (0, _) => minor_words,
+            _ => format!("{} {} {}", major_words, self.and, minor_words),
+        })
+    }
+
+    fn date_words(&self, day: u64, month: u64, year: Option<u64>) -> Option<String> {
+        if !(1..=31).contains(&day) {
+            return None;
+        }
+        let month = self.months.get((month as usize).checked_sub(1)?)?;
+        Some((self.date)(month, (self.ordinal)(day), year.map(self.year)))
+    }
+}
+
+/// Numbers directly after letters or inside of versions, paths
+/// and ranges are identifiers rather than amounts.
+fn is_boundary_before(before: Option<char>) -> bool {
+    before.is_none_or(|c| !(c.is_alphanumeric() || ".,-/:_".contains(c)))
+}
+
+fn is_boundary_after(after: &str) -> bool {
+    let mut chars = after.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), _) if c.is_alphanumeric() || c == '_' => false,
+        (Some(c), Some(next)) if ".,:-/".contains(c) && next.is_ascii_digit() => false,
+        _ => true,
+    }
+}
+
+const ONES_EN: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+
+const TENS_EN: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+const SCALES_EN: [(u64, &str); 4] = [
+    (1_000_000_000_000, "trillion"),
+    (1_000_000_000, "billion"),
+    (1_000_000, "million"),
+    (1_000, "thousand"),
+];
+
+fn en_below_hundred(n: u64) -> String {
+    match n {
+        0..=19 => ONES_EN[n as usize].to_string(),
+        _ if n.is_multiple_of(10) => TENS_EN[(n / 10) as usize].to_string(),
+        _ => format!(
+            "{}-{}",
+            TENS_EN[(n / 10) as usize],
+            ONES_EN[(n % 10) as usize]
+        ),
+    }
+}
+
+fn en_below_thousand(n: u64) -> String {
+    match (n / 100, n % 100) {
+        (0, rest) => en_below_hundred(rest),
+        (hundreds, 0) => format!("{} hundred", ONES_EN[hundreds as usize]),
+        (hundreds, rest) => format!(
+            "{} hundred {}",
+            ONES_EN[hundreds as usize],
+            en_below_hundred(rest)
+        ),
+    }
+}
+
+fn en_cardinal(mut n: u64) -> String {
+    if n == 0 {
+        return ONES_EN[0].to_string();
+    }
+
+    let mut words = Vec::new();
+    for &(scale, name) in SCALES_EN.iter() {
+        if n >= scale {
+            words.push(format!("{} {}", en_cardinal(n / scale), name));
+            n %= scale;
+        }
+    }
+    if n > 0 {
+        words.push(en_below_thousand(n));
+    }
+    words.join(" ")
+}
+
+fn en_ordinal(n: u64) -> String {
+    let cardinal = en_cardinal(n);
+    let last_start = cardinal.rfind([' ', '-']).map_or(0, |idx| idx + 1);
+    let (start, last) = cardinal.split_at(last_start);
+    let ordinal = match last {
+        "one" => "first".to_string(),
+        "two" => "second".to_string(),
+        "three" => "third".to_string(),
+        "five" => "fifth".to_string(),
+        "eight" => "eighth".to_string(),
+        "nine" => "ninth".to_string(),
+        "twelve" => "twelfth".to_string(),
+        tens if tens.ends_with('y') => format!("{}ieth", &tens[..tens.len() - 1]),
+        other => format!("{}th", other),
+    };
+    format!("{}{}", start, ordinal)
+}
+
+/// Reads years in pairs, like `nineteen eighty-four`.
+fn en_year(year: u64) -> String {
+    match (year, year / 100, year % 100) {
+        (2000..=2009, _, _) => en_cardinal(year),
+        (1100..=9999, century, 0) => format!("{} hundred", en_below_hundred(century)),
+        (1100..=9999, century, rest @ 1..=9) => format!(
+            "{} oh {}",
+            en_below_hundred(century),
+            ONES_EN[rest as usize]
+        ),
+        (1100..=9999, century, rest) => {
+            format!("{} {}", en_below_hundred(century), en_below_hundred(rest))
+        }
+        _ => en_cardinal(year),
+    }
+}
+
+fn en_date(month: &str, day: String, year: Option<String>) -> String {
+    match year {
+        Some(year) => format!("{} {}, {}", month, day, year),
+        None => format!("{} {}", month, day),
+    }
+}
+
+fn en_time(hours: u64, minutes: u64) -> String {
+    match minutes {
+        0 => format!("{} o'clock", en_cardinal(hours)),
+        1..=9 => format!("{} oh {}", en_cardinal(hours), en_cardinal(minutes)),
+        _ => format!("{} {}", en_cardinal(hours), en_cardinal(minutes)),
+    }
+}
+
+static ENGLISH: Rules = Rules {
+    number: r"\d{1,3}(?:,\d{3})+(?:\.\d+)?|\d+(?:\.\d+)?",
+    thousands_separator: ',',
+    decimal_separator: '.',
+    decimal_word: "point",
+    minus: "minus",
+    and: "and",
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    abbreviations: &[
+        ("e.g.", "for example"),
+        ("i.e.", "that is"),
+        ("etc.", "et cetera"),
+        ("vs.", "versus"),
+        ("approx.", "approximately"),
+        ("Dr.", "Doctor"),
+        ("Mr.", "Mister"),
+        ("Mrs.", "Missus"),
+        ("Prof.", "Professor"),
+    ],
+    units: &[
+        Unit {
+            symbol: "%",
+            one: "one percent",
+            many: "percent",
+        },
+        Unit {
+            symbol: "°C",
+            one: "one degree Celsius",
+            many: "degrees Celsius",
+        },
+        Unit {
+            symbol: "KB",
+            one: "one kilobyte",
+            many: "kilobytes",
+        },
+        Unit {
+            symbol: "MB",
+            one: "one megabyte",
+            many: "megabytes",
+        },
+        Unit {
+            symbol: "GB",
+            one: "one gigabyte",
+            many: "gigabytes",
+        },
+        Unit {
+            symbol: "TB",
+            one: "one terabyte",
+            many: "terabytes",
+        },
+        Unit {
+            symbol: "ms",
+            one: "one millisecond",
+            many: "milliseconds",
+        },
+        Unit {
+            symbol: "s",
+            one: "one second",
+            many: "seconds",
+        },
+        Unit {
+            symbol: "min",
+            one: "one minute",
+            many: "minutes",
+        },
+        Unit {
+            symbol: "h",
+            one: "one hour",
+            many: "hours",
+        },
+        Unit {
+            symbol: "km",
+            one: "one kilometer",
+            many: "kilometers",
+        },
+        Unit {
+            symbol: "kg",
+            one: "one kilogram",
+            many: "kilograms",
+        },
+        Unit {
+            symbol: "GHz",
+            one: "one gigahertz",
+            many: "gigahertz",
+        },
+        Unit {
+            symbol: "MHz",
+            one: "one megahertz",
+            many: "megahertz",
+        },
+    ],
+    currencies: &[
+        Currency {
+            symbols: &["€", "EUR"],
+            one: "one euro",
+            many: "euros",
+            minor_one: "one cent",
+            minor_many: "cents",
+        },
+        Currency {
+            symbols: &["$", "USD"],
+            one: "one dollar",
+            many: "dollars",
+            minor_one: "one cent",
+            minor_many: "cents",
+        },
+        Currency {
+            symbols: &["£", "GBP"],
+            one: "one pound",
+            many: "pounds",
+            minor_one: "one penny",
+            minor_many: "pence",
+        },
+    ],
+    cardinal: en_cardinal,
+    ordinal: en_ordinal,
+    year: en_year,
+    date: en_date,
+    time: en_time,
+    pattern: OnceLock::new(),
+};
+
+const ONES_DE: [&str; 20] = [
+    "null",
+    "eins",
+    "zwei",
+    "drei",
+    "vier",
+    "fünf",
+    "sechs",
+    "sieben",
+    "acht",
+    "neun",
+    "zehn",
+    "elf",
+    "zwölf",
+    "dreizehn",
+    "vierzehn",
+    "fünfzehn",
+    "sechzehn",
+    "siebzehn",
+    "achtzehn",
+    "neunzehn",
+];
+
+const TENS_DE: [&str; 10] = [
+    "", "", "zwanzig", "dreißig", "vierzig", "fünfzig", "sechzig", "siebzig", "achtzig", "neunzig",
+];
+
+const SCALES_DE: [(u64, &str, &str); 3] = [
+    (1_000_000_000_000, "eine Billion", "Billionen"),
+    (1_000_000_000, "eine Milliarde", "Milliarden"),
+    (1_000_000, "eine Million", "Millionen"),
+];
+
+fn de_below_hundred(n: u64) -> String {
+    match (n, n % 10) {
+        (0..=19, _) => ONES_DE[n as usize].to_string(),
+        (_, 0) => TENS_DE[(n / 10) as usize].to_string(),
+        (_, 1) => format!("einund{}", TENS_DE[(n / 10) as usize]),
+        (_, ones) => format!(
+            "{}und{}",
+            ONES_DE[ones as usize],
+            TENS_DE[(n / 10) as usize]
+        ),
+    }
+}
+
+/// Numbers below a thousand as part of a compound, where a
+/// trailing one is `ein` if more follows, like in `eintausend`.
+fn de_below_thousand(n: u64, followed: bool) -> String {
+    let hundreds = match n / 100 {
+        0 => String::new(),
+        1 => "einhundert".to_string(),
+        hundreds => format!("{}hundert", ONES_DE[hundreds as usize]),
+    };
+    let rest = match n % 100 {
+        0 => String::new(),
+        1 if followed => "ein".to_string(),
+        rest => de_below_hundred(rest),
+    };
+    hundreds + &rest
+}
+
+fn de_below_million(n: u64) -> String {
+    let thousands = match n / 1000 {
+        0 => String::new(),
+        thousands => format!("{}tausend", de_below_thousand(thousands, true)),
+    };
+    thousands + &de_below_thousand(n % 1000, false)
+}
+
+fn de_cardinal(mut n: u64) -> String {
+    if n == 0 {
+        return ONES_DE[0].to_string();
+    }
+
+    let mut words = Vec::new();
+    for &(scale, one, many) in SCALES_DE.iter() {
+        match n / scale {
+            0 => (),
+            1 => words.push(one.to_string()),
+            count => words.push(format!("{} {}", de_cardinal(count), many)),
+        }
+        n %= scale;
+    }
+    if n > 0 {
+        words.push(de_below_million(n));
+    }
+    words.join(" ")
+}
+
+fn de_ordinal(n: u64) -> String {
+    let rest = n % 100;
+    if (1..20).contains(&rest) {
+        let start = match n - rest {
+            0 => String::new(),
+            start => de_cardinal(start),
+        };
+        let end = match rest {
+            1 => "erster".to_string(),
+            3 => "dritter".to_string(),
+            7 => "siebter".to_string(),
+            8 => "achter".to_string(),
+            rest => format!("{}ter", ONES_DE[rest as usize]),
+        };
+        start + &end
+    } else {
+        format!("{}ster", de_cardinal(n))
+    }
+}
+
+/// Reads years before 2000 in hundreds, like `neunzehnhundertvierundachtzig`.
+fn de_year(year: u64) -> String {
+    match year {
+        1100..=1999 => format!(
+            "{}hundert{}",
+            de_below_hundred(year / 100),
+            de_below_thousand(year % 100, false)
+        ),
+        _ => de_cardinal(year),
+    }
+}
+
+/// Reads the day in the form used after `am` or `bis`, like
+/// `ersten Mai`.
+fn de_date(month: &str, day: String, year: Option<String>) -> String {
+    let day = match day.strip_suffix("er") {
+        Some(stem) => format!("{}en", stem),
+        None => day,
+    };
+    match year {
+        Some(year) => format!("{} {} {}", day, month, year),
+        None => format!("{} {}", day, month),
+    }
+}
+
+fn de_time(hours: u64, minutes: u64) -> String {
+    let hours = match hours {
+        1 => "ein".to_string(),
+        hours => de_cardinal(hours),
+    };
+    match minutes {
+        0 => format!("{} Uhr", hours),
+        minutes => format!("{} Uhr {}", hours, de_cardinal(minutes)),
+    }
+}
+
+static GERMAN: Rules = Rules {
+    number: r"\d{1,3}(?:\.\d{3})+(?:,\d+)?|\d+(?:,\d+)?",
+    thousands_separator: '.',
+    decimal_separator: ',',
+    decimal_word: "Komma",
+    minus: "minus",
+    and: "und",
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    abbreviations: &[
+        ("z.B.", "zum Beispiel"),
+        ("d.h.", "das heißt"),
+        ("u.a.", "unter anderem"),
+        ("usw.", "und so weiter"),
+        ("bzw.", "beziehungsweise"),
+        ("ca.", "circa"),
+        ("evtl.", "eventuell"),
+        ("ggf.", "gegebenenfalls"),
+        ("vgl.", "vergleiche"),
+        ("inkl.", "inklusive"),
+        ("Nr.", "Nummer"),
+        ("Dr.", "Doktor"),
+        ("Prof.", "Professor"),
+    ],
+    units: &[
+        Unit {
+            symbol: "%",
+            one: "ein Prozent",
+            many: "Prozent",
+        },
+        Unit {
+            symbol: "°C",
+            one: "ein Grad Celsius",
+            many: "Grad Celsius",
+        },
+        Unit {
+            symbol: "KB",
+            one: "ein Kilobyte",
+            many: "Kilobyte",
+        },
+        Unit {
+            symbol: "MB",
+            one: "ein Megabyte",
+            many: "Megabyte",
+        },
+        Unit {
+            symbol: "GB",
+            one: "ein Gigabyte",
+            many: "Gigabyte",
+        },
+        Unit {
+            symbol: "TB",
+            one: "ein Terabyte",
+            many: "Terabyte",
+        },
+        Unit {
+            symbol: "ms",
+            one: "eine Millisekunde",
+            many: "Millisekunden",
+        },
+        Unit {
+            symbol: "s",
+            one: "eine Sekunde",
+            many: "Sekunden",
+        },
+        Unit {
+            symbol: "min",
+            one: "eine Minute",
+            many: "Minuten",
+        },
+        Unit {
+            symbol: "h",
+            one: "eine Stunde",
+            many: "Stunden",
+        },
+        Unit {
+            symbol: "km",
+            one: "ein Kilometer",
+            many: "Kilometer",
+        },
+        Unit {
+            symbol: "kg",
+            one: "ein Kilogramm",
+            many: "Kilogramm",
+        },
+        Unit {
+            symbol: "GHz",
+            one: "ein Gigahertz",
+            many: "Gigahertz",
+        },
+        Unit {
+            symbol: "MHz",
+            one: "ein Megahertz",
+            many: "Megahertz",
+        },
+    ],
+    currencies: &[
+        Currency {
+            symbols: &["€", "EUR"],
+            one: "ein Euro",
+            many: "Euro",
+            minor_one: "ein Cent",
+            minor_many: "Cent",
+        },
+        Currency {
+            symbols: &["$", "USD"],
+            one: "ein Dollar",
+            many: "Dollar",
+            minor_one: "ein Cent",
+            minor_many: "Cent",
+        },
+        Currency {
+            symbols: &["£", "GBP"],
+            one: "ein Pfund",
+            many: "Pfund",
+            minor_one: "ein Penny",
+            minor_many: "Pence",
+        },
+    ],
+    cardinal: de_cardinal,
+    ordinal: de_ordinal,
+    year: de_year,
+    date: de_date,
+    time: de_time,
+    pattern: OnceLock::new(),
+};
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Utterance;
+
+    #[test]
+    fn english_amounts_and_dates() {
+        assert_eq!(
+            normalize("Pay €1,200.50 by 2026-10-17 at 9:05.", Language::English),
+            "Pay one thousand two hundred euros and fifty cents by \
+             October seventeenth, twenty twenty-six at nine oh five."
+        );
+    }
+
+    #[test]
+    fn german_amounts_and_dates() {
+        assert_eq!(
+            normalize(
+                "Zahlen Sie 1.200,50 € bis 17. Oktober 2026 um 14:30",
+                Language::German
+            ),
+            "Zahlen Sie eintausendzweihundert Euro und fünfzig Cent bis \
+             siebzehnten Oktober zweitausendsechsundzwanzig um vierzehn Uhr dreißig"
+        );
+        assert_eq!(
+            normalize("Am 01.05.1999, d.h. vor 21 Jahren", Language::German),
+            "Am ersten Mai neunzehnhundertneunundneunzig, das heißt vor einundzwanzig Jahren"
+        );
+    }
+
+    #[test]
+    fn units_ordinals_and_numbers() {
+        assert_eq!(
+            normalize(
+                "Only 3.5 GB and 1 % left, the 21st time, -4 or 1000000",
+                Language::English
+            ),
+            "Only three point five gigabytes and one percent left, the twenty-first time, \
+             minus four or one million"
+        );
+        assert_eq!(
+            normalize("Noch 101 MB für 3 min", Language::German),
+            "Noch einhunderteins Megabyte für drei Minuten"
+        );
+    }
+
+    #[test]
+    fn decades_are_not_units() {
+        assert_eq!(
+            normalize("In the 1990s and 80s, 5 s or 2 h", Language::English),
+            "In the 1990s and 80s, five seconds or two hours"
+        );
+        assert_eq!(
+            normalize("Nach 3 h und 20 s", Language::German),
+            "Nach drei Stunden und zwanzig Sekunden"
+        );
+    }
+
+    #[test]
+    fn identifiers_are_kept() {
+        let text = "Upgrade db1 to 1.2.3 for INC-4711 on 10.0.0.1, pages 5-10";
+        assert_eq!(normalize(text, Language::English), text);
+        match normalize("nothing to do", Language::German) {
+            Cow::Borrowed(_) => (),
+            Cow::Owned(_) => panic!("Expected unchanged text to be borrowed"),
+        }
+    }
+
+    #[test]
+    fn normalize_parsed_markup() {
+        let utterance = Utterance::parse("Only 3.5 GB left on {chars: db1}. _Act now_")
+            .normalize(Language::English);
+        assert_eq!(
+            utterance.tokens()[0],
+            Token::Normal("Only three point five gigabytes left on".into())
+        );
+    }
+
+    #[test]
+    fn language_tags() {
+        assert_eq!(Language::from_tag("de_AT.UTF-8"), Some(Language::German));
+        assert_eq!(Language::from_tag("EN-us"), Some(Language::English));
+        assert_eq!(Language::from_tag("fr"), None);
+    }
+}
@@ -0,0 +1,276 @@
+//! Pronunciations given inline as phonemes, like
+//! `{ipa:ˈtɑːvlə|tavla}`, and their validation and
+//! conversion between phonetic alphabets.
+
+pub use err::Error;
+
+/// The alphabet that the phonemes of a
+/// [`Token::Phoneme`](enum.Token.html) are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhoneticAlphabet {
+    /// The International Phonetic Alphabet, written `{ipa:...}`.
+    Ipa,
+    /// The ASCII phoneme mnemonics of espeak, written `{espeak:...}`.
+    Espeak,
+    /// The phoneme set of the mac `say` command, written `{say:...}`.
+    Say,
+    /// The American English phone set of SAPI, written `{sapi:...}`.
+    Sapi,
+}
+
+/// IPA symbols of General American English and their
+/// equivalents in the `say` and SAPI alphabets, longest first.
+const IPA_SYMBOLS: &[(&str, &str, &str)] = &[
+    ("tʃ", "C", "ch"),
+    ("dʒ", "J", "jh"),
+    ("eɪ", "EY", "ey"),
+    ("aɪ", "AY", "ay"),
+    ("aʊ", "AW", "aw"),
+    ("ɔɪ", "OY", "oy"),
+    ("oʊ", "OW", "ow"),
+    ("əʊ", "OW", "ow"),
+    ("ɑː", "AA", "aa"),
+    ("ɔː", "AO", "ao"),
+    ("iː", "IY", "iy"),
+    ("uː", "UW", "uw"),
+    ("ɜː", "UXr", "er"),
+    ("ɑ", "AA", "aa"),
+    ("ɒ", "AA", "aa"),
+    ("a", "AA", "aa"),
+    ("æ", "AE", "ae"),
+    ("ʌ", "UX", "ah"),
+    ("ɔ", "AO", "ao"),
+    ("ə", "AX", "ax"),
+    ("ɚ", "UXr", "er"),
+    ("ɝ", "UXr", "er"),
+    ("ɜ", "UXr", "er"),
+    ("ɛ", "EH", "eh"),
+    ("e", "EH", "eh"),
+    ("ɪ", "IH", "ih"),
+    ("i", "IY", "iy"),
+    ("ʊ", "UH", "uh"),
+    ("u", "UW", "uw"),
+    ("o", "OW", "ow"),
+    ("b", "b", "b"),
+    ("d", "d", "d"),
+    ("f", "f", "f"),
+    ("g", "g", "g"),
+    ("ɡ", "g", "g"),
+    ("h", "h", "h"),
+    ("k", "k", "k"),
+    ("l", "l", "l"),
+    ("m", "m", "m"),
+    ("n", "n", "n"),
+    ("p", "p", "p"),
+    ("s", "s", "s"),
+    ("t", "t", "t"),
+    ("v", "v", "v"),
+    ("w", "w", "w"),
+    ("z", "z", "z"),
+    ("ŋ", "N", "ng"),
+    ("ʃ", "S", "sh"),
+    ("ʒ", "Z", "zh"),
+    ("θ", "T", "th"),
+    ("ð", "D", "dh"),
+    ("j", "y", "y"),
+    ("ɹ", "r", "r"),
+    ("r", "r", "r"),
+    ("ɾ", "t", "t"),
+];
+
+const SAPI_PHONES: &[&str] = &[
+    "aa", "ae", "ah", "ao", "aw", "ax", "ay", "b", "ch", "d", "dh", "eh", "er", "ey", "f", "g",
+    "h", "ih", "iy", "jh", "k", "l", "m", "n", "ng", "ow", "oy", "p", "r", "s", "sh", "t", "th",
+    "uh", "uw", "v", "w", "y", "z", "zh",
+];
+
+impl PhoneticAlphabet {
+    pub(crate) fn from_keyword(keyword: &str) -> Option<PhoneticAlphabet> {
+        match keyword {
+            "ipa" => Some(PhoneticAlphabet::Ipa),
+            "espeak" => Some(PhoneticAlphabet::Espeak),
+            "say" => Some(PhoneticAlphabet::Say),
+            "sapi" => Some(PhoneticAlphabet::Sapi),
+            _ => None,
+        }
+    }
+
+    /// Checks that the phonemes only use symbols of this alphabet.
+    ///
+    /// This cannot tell whether a backend knows every combination
+    /// of the symbols, but catches typos and phonemes written in
+    /// the wrong alphabet.
+    pub fn validate(self, phonemes: &str) -> Result<(), Error> {
+        if phonemes.trim().is_empty() {
+            return Err(Error::empty(self));
+        }
+
+        if self == PhoneticAlphabet::Sapi {
+            return match phonemes
+                .split_whitespace()
+                .find(|phone| !SAPI_PHONES.contains(&phone.trim_end_matches(['1', '2'])))
+            {
+                Some(phone) => Err(Error::invalid_symbol(self, phone)),
+                None => Ok(()),
+            };
+        }
+
+        let is_valid = match self {
+            PhoneticAlphabet::Ipa => is_ipa,
+            PhoneticAlphabet::Espeak => is_espeak,
+            PhoneticAlphabet::Say | PhoneticAlphabet::Sapi => is_say,
+        };
+        match phonemes.chars().find(|&c| !is_valid(c)) {
+            Some(invalid) => Err(Error::invalid_symbol(self, &invalid.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+fn is_ipa(c: char) -> bool {
+    c.is_ascii_lowercase()
+        || c == ' '
+        || c == '.'
+        || "æçðøħŋœǀǁǂǃβθχ‿↑↓".contains(c)
+        || ('\u{250}'..='\u{36F}').contains(&c)
+        || ('\u{1D00}'..='\u{1DBF}').contains(&c)
+}
+
+fn is_espeak(c: char) -> bool {
+    c.is_ascii_graphic() && c != '[' && c != ']' || c == ' '
+}
+
+fn is_say(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "=%@_~>.,! ".contains(c)
+}
+
+/// Converts IPA to the phonemes of `say`, where stress is
+/// marked before the vowel. Returns `None` for symbols that
+/// have no equivalent.
+pub(crate) fn ipa_to_say(ipa: &str) -> Option<String> {
+    convert(ipa, |symbol, stress, out| {
+        out.push_str(stress.unwrap_or(""));
+        out.push_str(symbol.1);
+    })
+}
+
+/// Converts IPA to space separated SAPI phones, where stress
+/// is marked after the vowel. Returns `None` for symbols that
+/// have no equivalent.
+pub(crate) fn ipa_to_sapi(ipa: &str) -> Option<String> {
+    let sapi = convert(ipa, |symbol, stress, out| {
+        out.push_str(symbol.2);
+        out.push_str(stress.unwrap_or(""));
+        out.push(' ');
+    })?;
+    Some(sapi.trim_end().to_string())
+}
+
+fn convert<F>(ipa: &str, mut push: F) -> Option<String>
+where
+    F: FnMut(&(&str, &str, &str), Option<&str>, &mut String),
+{
+    let mut converted = String::new();
+    // IPA marks stress at the start of the syllable, both
+    // targets at its vowel
+    let mut stress = None;
+    let mut rest = ipa.trim();
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            'ˈ' => stress = Some("1"),
+            'ˌ' => stress = Some("2"),
+            'ː' | '.' | ' ' => (),
+            _ => {
+                let symbol = IPA_SYMBOLS
+                    .iter()
+                    .find(|symbol| rest.starts_with(symbol.0))?;
+                // only vowels take more than one letter in say
+                let is_vowel = symbol.1.len() > 1;
+                push(
+                    symbol,
+                    if is_vowel { stress.take() } else { None },
+                    &mut converted,
+                );
+                rest = &rest[symbol.0.len()..];
+                continue;
+            }
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    Some(converted)
+}
+
+mod err {
+    use super::PhoneticAlphabet;
+    use failure::{Backtrace, Fail};
+
+    /// Phonemes that are not valid in their alphabet.
+    #[derive(Fail, Debug)]
+    pub enum Error {
+        #[fail(display = "no phonemes given for {:?} pronunciation", alphabet)]
+        Empty {
+            alphabet: PhoneticAlphabet,
+            backtrace: Backtrace,
+        },
+        #[fail(
+            display = "{:?} is not a phoneme of the {:?} alphabet",
+            symbol, alphabet
+        )]
+        InvalidSymbol {
+            alphabet: PhoneticAlphabet,
+            symbol: String,
+            backtrace: Backtrace,
+        },
+    }
+
+    impl Error {
+        pub fn empty(alphabet: PhoneticAlphabet) -> Self {
+            Error::Empty {
+                alphabet,
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn invalid_symbol(alphabet: PhoneticAlphabet, symbol: &str) -> Self {
+            Error::InvalidSymbol {
+                alphabet,
+                symbol: symbol.into(),
+                backtrace: Backtrace::new(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validate_alphabets() {
+        assert!(PhoneticAlphabet::Ipa.validate("ˈtɑːvlə").is_ok());
+        assert!(PhoneticAlphabet::Espeak.validate("t'A:vl@").is_ok());
+        assert!(PhoneticAlphabet::Sapi.validate("t aa1 v l ax").is_ok());
+        assert!(PhoneticAlphabet::Say.validate("tAA1vlAX").is_ok());
+
+        match PhoneticAlphabet::Sapi.validate("t aa1 v l @") {
+            Err(Error::InvalidSymbol { ref symbol, .. }) => assert_eq!(symbol, "@"),
+            other => panic!("Expected invalid symbol, got: {:?}", other),
+        }
+        assert!(PhoneticAlphabet::Espeak.validate("t'A:]]vl@").is_err());
+        assert!(PhoneticAlphabet::Ipa.validate("TAVLA").is_err());
+        assert!(PhoneticAlphabet::Ipa.validate("  ").is_err());
+    }
+
+    #[test]
+    fn convert_ipa() {
+        assert_eq!(ipa_to_say("ˈtɑːvlə").as_deref(), Some("t1AAvlAX"));
+        assert_eq!(ipa_to_sapi("ˈtɑːvlə").as_deref(), Some("t aa1 v l ax"));
+        assert_eq!(
+            ipa_to_sapi("ˌdʒɔɪˈstɪk").as_deref(),
+            Some("jh oy2 s t ih1 k")
+        );
+        assert_eq!(ipa_to_say("ʔa"), None);
+    }
+}
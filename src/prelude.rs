@@ -1,11 +1,30 @@
 pub use crate::any::{AnySpeech, AnyVoice};
-pub use crate::child::Error as ChildError;
+pub use crate::child::{Error as ChildError, Stderr};
+pub use crate::chunked::{ChunkedSpeech, Error as ChunkedSpeechError};
 #[cfg(target_os = "windows")]
 pub use crate::cscript::{CScriptVoice, Error as CScriptVoiceError, Speech as CScriptVoiceSpeech};
-pub use crate::espeak::{Error as EspeakError, Espeak, Speech as EspeakSpeech};
+pub use crate::espeak::{
+    Error as EspeakError, Espeak, EspeakBuilder, EspeakFeatures, EspeakFlavor, EspeakOptions,
+    Speech as EspeakSpeech,
+};
 pub use crate::factory::*;
+pub use crate::lexicon::{Error as LexiconError, Lexicon, Pattern, Pronunciation};
+pub use crate::normalize::{normalize, Language};
+pub use crate::phoneme::{Error as PhonemeError, PhoneticAlphabet};
+pub use crate::preset::Preset;
 #[cfg(target_os = "macos")]
 pub use crate::say::{Error as SayError, Say, Speech as SaySpeech};
+pub use crate::say_as::Interpretation;
+pub use crate::segment::{Boundary, Segment};
+pub use crate::settings::{PauseProfile, Settings};
 pub use crate::speech::Speech;
+pub use crate::ssml::Error as SsmlError;
+pub use crate::symbols::{verbalize_symbols, SymbolVerbosity};
+pub use crate::token::{LineMode, PauseDuration, Prosody, SpannedTokens, Token, Tokenizer};
+pub use crate::transcribe::{Transcriber, Transcription};
+pub use crate::utterance::Utterance;
+pub use crate::validate::{validate, Diagnostic, Problem, Severity};
+pub use crate::version::{BackendVersion, Error as VersionError};
 pub use crate::voice::Voice;
+pub use crate::voices::{Age, Backend, Gender, VoiceInfo};
 pub use failure::Error;
@@ -0,0 +1,81 @@
+//! Named ways of speaking that work with every backend, like
+//! a calm or an urgent voice.
+
+use crate::espeak::EspeakOptions;
+use crate::token::Prosody;
+
+/// A way of speaking that [`AnyVoice::with_preset`](enum.AnyVoice.html#method.with_preset)
+/// maps onto the options of the backend.
+///
+/// Backends with options for rate, pitch and volume, like
+/// `espeak`, use those, the others speak everything with
+/// the corresponding [`Prosody`](enum.Prosody.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Slower, lower and softer than usual, e.g. for reading
+    /// at night.
+    Calm,
+    /// Faster, higher and louder than usual, e.g. for alerts.
+    Urgent,
+    /// Much faster than usual, for listeners who are used to
+    /// synthesized speech.
+    FastReader,
+}
+
+impl Preset {
+    /// Parses names like `calm`, `urgent` and `fast reader`,
+    /// which may also be written with a dash or an underscore.
+    pub fn from_name(name: &str) -> Option<Preset> {
+        match name.to_lowercase().replace(['-', '_'], " ").as_str() {
+            "calm" => Some(Preset::Calm),
+            "urgent" => Some(Preset::Urgent),
+            "fast reader" => Some(Preset::FastReader),
+            _ => None,
+        }
+    }
+
+    /// The options for `espeak` with this way of speaking.
+    pub fn espeak_options(self) -> EspeakOptions {
+        let options = EspeakOptions::new();
+        let options = match self {
+            Preset::Calm => options
+                .speed(145)
+                .and_then(|options| options.pitch(40))
+                .and_then(|options| options.amplitude(80))
+                .and_then(|options| options.word_gap(2)),
+            Preset::Urgent => options
+                .speed(210)
+                .and_then(|options| options.pitch(65))
+                .and_then(|options| options.amplitude(150)),
+            Preset::FastReader => options.speed(320),
+        };
+        options.expect("presets are in range")
+    }
+
+    /// The prosody to speak with on backends without options,
+    /// outermost first.
+    pub fn prosody(self) -> &'static [Prosody] {
+        match self {
+            Preset::Calm => &[Prosody::Slow, Prosody::Low, Prosody::Soft],
+            Preset::Urgent => &[Prosody::Fast, Prosody::High, Prosody::Loud],
+            Preset::FastReader => &[Prosody::Fast],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names_and_options() {
+        assert_eq!(Preset::from_name("Fast-Reader"), Some(Preset::FastReader));
+        assert_eq!(Preset::from_name("calm"), Some(Preset::Calm));
+        assert_eq!(Preset::from_name("sleepy"), None);
+
+        for preset in &[Preset::Calm, Preset::Urgent, Preset::FastReader] {
+            assert_ne!(preset.espeak_options(), EspeakOptions::new());
+            assert!(!preset.prosody().is_empty());
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! Turns a stream of tokens into the markup dialect of
+//! a specific backend.
+//!
+//! The renderers do not depend on the platform, so that
+//! the output for every backend can be checked anywhere.
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub mod sapi;
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub mod say;
+pub mod ssml;
+
+use std::ops::Range;
+
+/// The name of a marker for the given source range, like `12-34`.
+fn mark_name(span: &Range<usize>) -> String {
+    format!("{}-{}", span.start, span.end)
+}
@@ -0,0 +1,178 @@
+//! SAPI XML for the Windows speech API.
+
+use super::mark_name;
+use crate::phoneme::{ipa_to_sapi, PhoneticAlphabet};
+use crate::sanitize::{sanitize, Dialect};
+use crate::say_as::Interpretation;
+use crate::settings::PauseProfile;
+use crate::token::{Prosody, Token};
+
+/// Renders the tokens into a `<sapi>` XML document, with pause
+/// lengths taken from the given profile.
+pub fn render(tokens: &[Token], pauses: &PauseProfile) -> String {
+    let mut xml = String::new();
+
+    xml.push_str("<sapi>");
+    for token in tokens {
+        match *token {
+            Token::Normal(ref text) => {
+                xml.push(' ');
+                xml.push_str(&sanitize(text, Dialect::Sapi));
+            }
+            Token::Emphasised(ref text) => {
+                xml.push_str("<emph>");
+                xml.push_str(&sanitize(text, Dialect::Sapi));
+                xml.push_str("</emph>");
+            }
+            Token::Strong(ref text) => {
+                xml.push_str("<emph><pitch middle=\"2\">");
+                xml.push_str(&sanitize(text, Dialect::Sapi));
+                xml.push_str("</pitch></emph>");
+            }
+            Token::Reduced(ref text) => {
+                xml.push_str("<volume level=\"70\"> ");
+                xml.push_str(&sanitize(text, Dialect::Sapi));
+                xml.push_str("</volume>");
+            }
+            Token::Pause(pause) => {
+                xml.push_str(&format!(
+                    "<silence msec=\"{}\" />",
+                    pauses.duration(pause).as_millis()
+                ));
+            }
+            Token::ProsodyStart(prosody) => {
+                xml.push_str(&format!(
+                    "<{} {}>",
+                    prosody_element(prosody),
+                    prosody_attribute(prosody)
+                ));
+            }
+            Token::ProsodyEnd(prosody) => {
+                xml.push_str(&format!("</{}>", prosody_element(prosody)));
+            }
+            Token::SayAs(Interpretation::Characters, ref text)
+            | Token::SayAs(Interpretation::Digits, ref text) => {
+                xml.push_str("<spell>");
+                xml.push_str(&sanitize(text, Dialect::Sapi));
+                xml.push_str("</spell>");
+            }
+            Token::SayAs(interpretation, ref text) => {
+                xml.push(' ');
+                xml.push_str(&sanitize(&interpretation.expand(text), Dialect::Sapi));
+            }
+            Token::Phoneme {
+                alphabet,
+                ref phonemes,
+                ref text,
+            } => {
+                let phonemes = match alphabet {
+                    PhoneticAlphabet::Sapi => Some(phonemes.to_string()),
+                    PhoneticAlphabet::Ipa => ipa_to_sapi(phonemes),
+                    _ => None,
+                }
+                .filter(|phonemes| PhoneticAlphabet::Sapi.validate(phonemes).is_ok());
+
+                xml.push(' ');
+                match phonemes {
+                    Some(phonemes) => xml.push_str(&format!("<pron sym=\"{}\"/>", phonemes)),
+                    None => xml.push_str(&sanitize(text, Dialect::Sapi)),
+                }
+            }
+            Token::Mark(ref span) => {
+                xml.push_str(&format!("<bookmark mark=\"{}\"/>", mark_name(span)))
+            }
+        }
+    }
+    xml.push_str("</sapi>");
+
+    xml
+}
+
+/// SAPI speaks at full volume by default and cannot get any
+/// louder, so loud text is raised in pitch like strong emphasis.
+fn prosody_element(prosody: Prosody) -> &'static str {
+    match prosody {
+        Prosody::Slow | Prosody::Fast => "rate",
+        Prosody::Low | Prosody::High | Prosody::Loud => "pitch",
+        Prosody::Soft => "volume",
+    }
+}
+
+fn prosody_attribute(prosody: Prosody) -> &'static str {
+    match prosody {
+        Prosody::Slow => "speed=\"-4\"",
+        Prosody::Fast => "speed=\"4\"",
+        Prosody::Low => "middle=\"-4\"",
+        Prosody::High => "middle=\"4\"",
+        Prosody::Soft => "level=\"50\"",
+        Prosody::Loud => "middle=\"2\"",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Utterance;
+
+    fn render_markup(markup: &str) -> String {
+        render(Utterance::parse(markup).tokens(), &PauseProfile::default())
+    }
+
+    #[test]
+    fn golden_markup() {
+        assert_eq!(
+            render_markup("Hello _there_. Pause... over"),
+            "<sapi> Hello<emph>there</emph><silence msec=\"350\" /> \
+             Pause<silence msec=\"1000\" /> over</sapi>"
+        );
+    }
+
+    #[test]
+    fn golden_emphasis_levels() {
+        assert_eq!(
+            render_markup("{reduced: Disk usage is} _high_, __critical__"),
+            "<sapi><volume level=\"70\"> Disk usage is</volume><emph>high</emph> ,\
+             <emph><pitch middle=\"2\">critical</pitch></emph></sapi>"
+        );
+    }
+
+    #[test]
+    fn golden_escaped() {
+        assert_eq!(
+            render_markup("a < b & _<c>_ \u{7}[[x]]"),
+            "<sapi> a &lt; b &amp;<emph>&lt;c&gt;</emph> [[x]]</sapi>"
+        );
+    }
+
+    #[test]
+    fn golden_prosody() {
+        assert_eq!(
+            render_markup("{fast: Note that} {slow: {high: the _disk_ is full}}"),
+            "<sapi><rate speed=\"4\"> Note that</rate><rate speed=\"-4\">\
+             <pitch middle=\"4\"> the<emph>disk</emph> is full</pitch></rate></sapi>"
+        );
+        assert_eq!(
+            render_markup("{soft: quiet} {loud: alert}"),
+            "<sapi><volume level=\"50\"> quiet</volume><pitch middle=\"2\"> alert</pitch></sapi>"
+        );
+    }
+
+    #[test]
+    fn golden_phoneme() {
+        assert_eq!(
+            render_markup("I am {ipa:ˈtɑːvlə|tavla}, {say:tAA1vlAX|tavla}"),
+            "<sapi> I am <pron sym=\"t aa1 v l ax\"/> , tavla</sapi>"
+        );
+    }
+
+    #[test]
+    fn golden_marks() {
+        assert_eq!(
+            render(
+                Utterance::parse_with_marks("Hi _you_").tokens(),
+                &PauseProfile::default()
+            ),
+            "<sapi><bookmark mark=\"0-2\"/> Hi<bookmark mark=\"4-7\"/><emph>you</emph></sapi>"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Embedded commands for the mac `say` command.
+
+use crate::phoneme::{ipa_to_say, PhoneticAlphabet};
+use crate::sanitize::{sanitize, Dialect};
+use crate::say_as::Interpretation;
+use crate::settings::PauseProfile;
+use crate::token::{Prosody, Token};
+
+/// Renders the tokens into text with embedded `[[...]]` commands,
+/// with pause lengths taken from the given profile.
+pub fn render(tokens: &[Token], pauses: &PauseProfile) -> String {
+    let mut markup = String::new();
+    for token in tokens {
+        match *token {
+            Token::Normal(ref text) => {
+                markup.push(' ');
+                markup.push_str(&sanitize(text, Dialect::Say));
+            }
+            Token::Emphasised(ref text) => {
+                markup.push_str("[[emph +]] ");
+                markup.push_str(&sanitize(text, Dialect::Say));
+                markup.push_str("[[emph -]]");
+            }
+            Token::Strong(ref text) => {
+                markup.push_str("[[emph +]][[volm +0.2]] ");
+                markup.push_str(&sanitize(text, Dialect::Say));
+                markup.push_str("[[volm -0.2]][[emph -]]");
+            }
+            Token::Reduced(ref text) => {
+                // emph - only lasts for a single word, volume holds
+                markup.push_str("[[volm -0.2]] ");
+                markup.push_str(&sanitize(text, Dialect::Say));
+                markup.push_str("[[volm +0.2]]");
+            }
+            Token::Pause(pause) => {
+                markup.push_str(&format!("[[slnc {}]]", pauses.duration(pause).as_millis()))
+            }
+            Token::ProsodyStart(prosody) => markup.push_str(&prosody_command(prosody, false)),
+            Token::ProsodyEnd(prosody) => markup.push_str(&prosody_command(prosody, true)),
+            Token::SayAs(Interpretation::Characters, ref text)
+            | Token::SayAs(Interpretation::Digits, ref text) => {
+                markup.push_str("[[char LTRL]] ");
+                markup.push_str(&sanitize(text, Dialect::Say));
+                markup.push_str("[[char NORM]]");
+            }
+            Token::SayAs(interpretation, ref text) => {
+                markup.push(' ');
+                markup.push_str(&sanitize(&interpretation.expand(text), Dialect::Say));
+            }
+            Token::Phoneme {
+                alphabet,
+                ref phonemes,
+                ref text,
+            } => {
+                let phonemes = match alphabet {
+                    PhoneticAlphabet::Say => Some(phonemes.to_string()),
+                    PhoneticAlphabet::Ipa => ipa_to_say(phonemes),
+                    _ => None,
+                }
+                .filter(|phonemes| PhoneticAlphabet::Say.validate(phonemes).is_ok());
+
+                markup.push(' ');
+                match phonemes {
+                    Some(phonemes) => {
+                        markup.push_str(&format!("[[inpt PHON]]{}[[inpt TEXT]]", phonemes))
+                    }
+                    None => markup.push_str(&sanitize(text, Dialect::Say)),
+                }
+            }
+            // say only passes numbers on, use the start of the range
+            Token::Mark(ref span) => markup.push_str(&format!("[[sync 0x{:08x}]]", span.start)),
+        }
+    }
+    markup
+}
+
+/// Changes rate, pitch base or volume relative to the current
+/// value, or undoes the change when `revert` is set.
+fn prosody_command(prosody: Prosody, revert: bool) -> String {
+    let (command, raise, amount) = match prosody {
+        Prosody::Slow => ("rate", false, "50"),
+        Prosody::Fast => ("rate", true, "50"),
+        Prosody::Low => ("pbas", false, "10"),
+        Prosody::High => ("pbas", true, "10"),
+        Prosody::Soft => ("volm", false, "0.3"),
+        Prosody::Loud => ("volm", true, "0.3"),
+    };
+
+    let sign = if raise != revert { '+' } else { '-' };
+    format!("[[{} {}{}]]", command, sign, amount)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Utterance;
+
+    fn render_markup(markup: &str) -> String {
+        render(Utterance::parse(markup).tokens(), &PauseProfile::default())
+    }
+
+    #[test]
+    fn golden_markup() {
+        assert_eq!(
+            render_markup("Hello _there_. Pause... over"),
+            " Hello[[emph +]] there[[emph -]][[slnc 350]] Pause[[slnc 1000]] over"
+        );
+    }
+
+    #[test]
+    fn golden_emphasis_levels() {
+        assert_eq!(
+            render_markup("{reduced: Disk usage is} _high_, __critical__"),
+            "[[volm -0.2]] Disk usage is[[volm +0.2]][[emph +]] high[[emph -]] ,\
+             [[emph +]][[volm +0.2]] critical[[volm -0.2]][[emph -]]"
+        );
+    }
+
+    #[test]
+    fn golden_escaped() {
+        assert_eq!(
+            render_markup("volume [[volm 1]] & _[[rate 900]]_ <b>"),
+            " volume [ [volm 1] ] &[[emph +]] [ [rate 900] ][[emph -]] <b>"
+        );
+    }
+
+    #[test]
+    fn golden_trailing_bracket() {
+        assert_eq!(
+            render_markup("array[ _x_ [ {nato: b}"),
+            " array[ [[emph +]] x[[emph -]] [  Bravo"
+        );
+    }
+
+    #[test]
+    fn golden_prosody() {
+        assert_eq!(
+            render_markup("{fast: Note that} {low: {soft: the _disk_ is full}}"),
+            "[[rate +50]] Note that[[rate -50]][[pbas -10]][[volm -0.3]] the\
+             [[emph +]] disk[[emph -]] is full[[volm +0.3]][[pbas +10]]"
+        );
+    }
+
+    #[test]
+    fn golden_say_as() {
+        assert_eq!(
+            render_markup("Host {nato: db1} ticket {digits: 4711}"),
+            " Host Delta Bravo One ticket[[char LTRL]] 4711[[char NORM]]"
+        );
+    }
+
+    #[test]
+    fn golden_phoneme() {
+        assert_eq!(
+            render_markup("I am {ipa:ˈtɑːvlə|tavla}, {sapi:t aa1 v l ax|tavla}"),
+            " I am [[inpt PHON]]t1AAvlAX[[inpt TEXT]] , tavla"
+        );
+    }
+
+    #[test]
+    fn golden_marks() {
+        assert_eq!(
+            render(
+                Utterance::parse_with_marks("Hi _you_").tokens(),
+                &PauseProfile::default()
+            ),
+            "[[sync 0x00000000]] Hi[[sync 0x00000004]][[emph +]] you[[emph -]]"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! SSML for `espeak -m`.
+
+use super::mark_name;
+use crate::phoneme::PhoneticAlphabet;
+use crate::sanitize::{sanitize, Dialect};
+use crate::say_as::Interpretation;
+use crate::settings::PauseProfile;
+use crate::token::{Prosody, Token};
+
+/// Renders the tokens into an SSML `<speak>` document, with
+/// pause lengths taken from the given profile.
+pub fn render(tokens: &[Token], pauses: &PauseProfile) -> String {
+    let mut ssml = String::from("<speak>");
+    for token in tokens {
+        match *token {
+            Token::Normal(ref text) => {
+                ssml.push(' ');
+                ssml.push_str(&sanitize(text, Dialect::Ssml));
+            }
+            Token::Emphasised(ref text) => {
+                ssml.push_str("<emphasis> ");
+                ssml.push_str(&sanitize(text, Dialect::Ssml));
+                ssml.push_str("</emphasis>");
+            }
+            Token::Strong(ref text) => {
+                ssml.push_str("<emphasis level=\"strong\"> ");
+                ssml.push_str(&sanitize(text, Dialect::Ssml));
+                ssml.push_str("</emphasis>");
+            }
+            Token::Reduced(ref text) => {
+                ssml.push_str("<emphasis level=\"reduced\"> ");
+                ssml.push_str(&sanitize(text, Dialect::Ssml));
+                ssml.push_str("</emphasis>");
+            }
+            Token::Pause(pause) => ssml.push_str(&format!(
+                "<break time=\"{}ms\"/>",
+                pauses.duration(pause).as_millis()
+            )),
+            Token::ProsodyStart(prosody) => {
+                ssml.push_str(&format!("<prosody {}>", prosody_attribute(prosody)))
+            }
+            Token::ProsodyEnd(_) => ssml.push_str("</prosody>"),
+            Token::SayAs(Interpretation::Characters, ref text)
+            | Token::SayAs(Interpretation::Digits, ref text) => {
+                ssml.push_str("<say-as interpret-as=\"characters\"> ");
+                ssml.push_str(&sanitize(text, Dialect::Ssml));
+                ssml.push_str("</say-as>");
+            }
+            // espeak ignores other interpretations, read the expansion
+            Token::SayAs(interpretation, ref text) => {
+                ssml.push(' ');
+                ssml.push_str(&sanitize(&interpretation.expand(text), Dialect::Ssml));
+            }
+            Token::Phoneme {
+                alphabet,
+                ref phonemes,
+                ref text,
+            } => {
+                ssml.push(' ');
+                match alphabet {
+                    PhoneticAlphabet::Ipa if alphabet.validate(phonemes).is_ok() => {
+                        ssml.push_str(&format!(
+                            "<phoneme alphabet=\"ipa\" ph=\"{}\">{}</phoneme>",
+                            sanitize(phonemes, Dialect::Ssml),
+                            sanitize(text, Dialect::Ssml)
+                        ));
+                    }
+                    PhoneticAlphabet::Espeak if alphabet.validate(phonemes).is_ok() => {
+                        ssml.push_str(&format!("[[{}]]", escape_mnemonics(phonemes)));
+                    }
+                    _ => ssml.push_str(&sanitize(text, Dialect::Ssml)),
+                }
+            }
+            Token::Mark(ref span) => {
+                ssml.push_str(&format!("<mark name=\"{}\"/>", mark_name(span)))
+            }
+        }
+    }
+    ssml.push_str("</speak>");
+    ssml
+}
+
+/// Escapes espeak phoneme mnemonics for XML text content,
+/// keeping quotes as they mark stress. Validation already
+/// rules out brackets.
+fn escape_mnemonics(phonemes: &str) -> String {
+    phonemes
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn prosody_attribute(prosody: Prosody) -> &'static str {
+    match prosody {
+        Prosody::Slow => "rate=\"slow\"",
+        Prosody::Fast => "rate=\"fast\"",
+        Prosody::Low => "pitch=\"low\"",
+        Prosody::High => "pitch=\"high\"",
+        Prosody::Soft => "volume=\"soft\"",
+        Prosody::Loud => "volume=\"loud\"",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Utterance;
+
+    fn render_markup(markup: &str) -> String {
+        render(Utterance::parse(markup).tokens(), &PauseProfile::default())
+    }
+
+    #[test]
+    fn golden_markup() {
+        assert_eq!(
+            render_markup("Hello _there_. Pause... over"),
+            "<speak> Hello<emphasis> there</emphasis><break time=\"350ms\"/> \
+             Pause<break time=\"1000ms\"/> over</speak>"
+        );
+    }
+
+    #[test]
+    fn golden_pause_profile() {
+        assert_eq!(
+            render(
+                Utterance::parse("One. Two\n\nThree.... {pause 1.25s} Four").tokens(),
+                &PauseProfile::brisk()
+            ),
+            "<speak> One<break time=\"200ms\"/> Two<break time=\"400ms\"/> \
+             Three<break time=\"1000ms\"/><break time=\"1250ms\"/> Four</speak>"
+        );
+    }
+
+    #[test]
+    fn golden_emphasis_levels() {
+        assert_eq!(
+            render_markup("{reduced: Disk usage is} _high_, __critical__"),
+            "<speak><emphasis level=\"reduced\"> Disk usage is</emphasis>\
+             <emphasis> high</emphasis> ,<emphasis level=\"strong\"> critical</emphasis></speak>"
+        );
+    }
+
+    #[test]
+    fn golden_escaped() {
+        assert_eq!(
+            render_markup("if a < b && _c > d_ then [[rate 900]]"),
+            "<speak> if a &lt; b &amp;&amp;<emphasis> c &gt; d</emphasis> \
+             then [ [rate 900] ]</speak>"
+        );
+    }
+
+    #[test]
+    fn golden_injection_attempt() {
+        assert_eq!(
+            render_markup("</speak><audio src=\"x\"/>"),
+            "<speak> &lt;/speak&gt;&lt;audio src=&quot;x&quot;/&gt;</speak>"
+        );
+    }
+
+    #[test]
+    fn golden_prosody() {
+        assert_eq!(
+            render_markup("{fast: Note that} {slow: {loud: the _disk_ is full}}"),
+            "<speak><prosody rate=\"fast\"> Note that</prosody><prosody rate=\"slow\">\
+             <prosody volume=\"loud\"> the<emphasis> disk</emphasis> is full</prosody>\
+             </prosody></speak>"
+        );
+    }
+
+    #[test]
+    fn golden_say_as() {
+        assert_eq!(
+            render_markup("Ticket {chars: INC<1>} on {date: 2024-03-05}"),
+            "<speak> Ticket<say-as interpret-as=\"characters\"> INC&lt;1&gt;</say-as> \
+             on March 5, 2024</speak>"
+        );
+    }
+
+    #[test]
+    fn golden_phoneme() {
+        assert_eq!(
+            render_markup("I am {ipa:ˈtɑːvlə|tavla}, {espeak:t'A:vl@|tavla}, {say:tAA1vlAX|tavla}"),
+            "<speak> I am <phoneme alphabet=\"ipa\" ph=\"ˈtɑːvlə\">tavla</phoneme> \
+             , [[t'A:vl@]] , tavla</speak>"
+        );
+    }
+
+    #[test]
+    fn golden_marks() {
+        assert_eq!(
+            render(
+                Utterance::parse_with_marks("Hi _you_").tokens(),
+                &PauseProfile::default()
+            ),
+            "<speak><mark name=\"0-2\"/> Hi<mark name=\"4-7\"/><emphasis> you</emphasis></speak>"
+        );
+    }
+}
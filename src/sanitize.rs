@@ -0,0 +1,152 @@
+//! Escaping of user-provided text before it is embedded
+//! into the markup understood by the different backends.
+//!
+//! Text from the tokenizer is meant to be spoken, never
+//! interpreted. Every renderer passes its text through
+//! [`sanitize`](fn.sanitize.html) with its own dialect, so
+//! that characters like `<`, `&` or embedded commands like
+//! `[[rate 900]]` are read out instead of being executed.
+
+use std::borrow::Cow;
+
+/// The markup dialect that text is embedded into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// SSML as understood by `espeak -m`. Besides XML entities,
+    /// `espeak` treats `[[...]]` as phoneme input, even inside
+    /// of SSML, so brackets are neutralized too.
+    Ssml,
+    /// The embedded command syntax of the mac `say` command,
+    /// where `[[...]]` starts a command.
+    Say,
+    /// SAPI XML as spoken by the Windows speech API.
+    Sapi,
+}
+
+/// Makes the given text safe for embedding into markup of
+/// the given dialect.
+///
+/// Control characters are always removed, with whitespace
+/// control characters like tabs or carriage returns being
+/// replaced with a space. Borrows if nothing had to change.
+pub fn sanitize(text: &str, dialect: Dialect) -> Cow<'_, str> {
+    if !needs_sanitizing(text, dialect) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut sanitized = String::with_capacity(text.len() + 8);
+    let mut last = None;
+    for c in text.chars() {
+        if c.is_control() {
+            if c.is_whitespace() {
+                sanitized.push(' ');
+                last = Some(' ');
+            }
+            continue;
+        }
+
+        let escapes_xml = dialect == Dialect::Ssml || dialect == Dialect::Sapi;
+        let escapes_brackets = dialect == Dialect::Ssml || dialect == Dialect::Say;
+
+        if escapes_brackets && is_bracket(c) && last == Some(c) {
+            // Break up [[ and ]] so they can never form a command
+            sanitized.push(' ');
+        }
+
+        match c {
+            '&' if escapes_xml => sanitized.push_str("&amp;"),
+            '<' if escapes_xml => sanitized.push_str("&lt;"),
+            '>' if escapes_xml => sanitized.push_str("&gt;"),
+            '"' if escapes_xml => sanitized.push_str("&quot;"),
+            '\'' if escapes_xml => sanitized.push_str("&apos;"),
+            c => sanitized.push(c),
+        }
+        last = Some(c);
+    }
+
+    if ends_in_open_bracket(&sanitized, dialect) {
+        // A command emitted right after the text would form [[[
+        sanitized.push(' ');
+    }
+
+    Cow::Owned(sanitized)
+}
+
+fn needs_sanitizing(text: &str, dialect: Dialect) -> bool {
+    if ends_in_open_bracket(text, dialect) {
+        return true;
+    }
+
+    let mut last = None;
+    for c in text.chars() {
+        let needs = c.is_control()
+            || match dialect {
+                Dialect::Ssml => is_xml_special(c) || (is_bracket(c) && last == Some(c)),
+                Dialect::Say => is_bracket(c) && last == Some(c),
+                Dialect::Sapi => is_xml_special(c),
+            };
+        if needs {
+            return true;
+        }
+        last = Some(c);
+    }
+    false
+}
+
+fn ends_in_open_bracket(text: &str, dialect: Dialect) -> bool {
+    dialect == Dialect::Say && text.ends_with('[')
+}
+
+fn is_xml_special(c: char) -> bool {
+    matches!(c, '&' | '<' | '>' | '"' | '\'')
+}
+
+fn is_bracket(c: char) -> bool {
+    c == '[' || c == ']'
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn plain_text_is_borrowed() {
+        for dialect in &[Dialect::Ssml, Dialect::Say, Dialect::Sapi] {
+            match sanitize("Hello, world!", *dialect) {
+                Cow::Borrowed(text) => assert_eq!(text, "Hello, world!"),
+                Cow::Owned(_) => panic!("Expected unchanged text to be borrowed"),
+            }
+        }
+    }
+
+    #[test]
+    fn ssml_escapes_entities_and_brackets() {
+        assert_eq!(
+            sanitize("a < b && \"c\" > 'd' [[x]]", Dialect::Ssml),
+            "a &lt; b &amp;&amp; &quot;c&quot; &gt; &apos;d&apos; [ [x] ]"
+        );
+    }
+
+    #[test]
+    fn say_neutralizes_embedded_commands() {
+        assert_eq!(
+            sanitize("[[rate 900]] <ok> & [a]", Dialect::Say),
+            "[ [rate 900] ] <ok> & [a]"
+        );
+        assert_eq!(sanitize("[[[", Dialect::Say), "[ [ [ ");
+        assert_eq!(sanitize("array[", Dialect::Say), "array[ ");
+    }
+
+    #[test]
+    fn sapi_escapes_entities() {
+        assert_eq!(
+            sanitize("<silence msec=\"9999\"/> [[x]]", Dialect::Sapi),
+            "&lt;silence msec=&quot;9999&quot;/&gt; [[x]]"
+        );
+    }
+
+    #[test]
+    fn control_characters_are_stripped() {
+        assert_eq!(sanitize("a\u{7}b\tc\r\u{1b}[0m", Dialect::Say), "ab c [0m");
+    }
+}
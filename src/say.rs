@@ -4,31 +4,85 @@
 pub use crate::child::Speech;
 pub use err::Error;
 
-use crate::token::{PauseDuration::*, Token, Tokenizer};
+use crate::child::StderrReader;
+use crate::render;
+use crate::settings::Settings;
 use crate::version::detect_version_with_arg;
+use crate::voices::{parse_say_voices, VoiceInfo};
+use crate::Utterance;
 use std::io::Write;
 use std::path::Path;
 use std::process::{Child, ChildStdin, Command, Stdio};
 
-#[derive(Debug)]
-pub struct Say;
+#[derive(Debug, Clone)]
+pub struct Say {
+    settings: Settings,
+    /// The voice passed with `-v`, or `None` for the voice
+    /// selected in the system preferences.
+    voice: Option<String>,
+}
 
 /// A [`Voice`](trait.Voice.html) that works by opening
 /// a shell and piping text into `say`.
 impl Say {
+    /// Checks that `say` is installed. It reports no version,
+    /// so there is no minimum version to check.
     pub fn new() -> Result<Say, Error> {
         detect_version_with_arg("say", Some(""))
-            .map(|_| Say)
+            .map(|_| Say {
+                settings: Settings::default(),
+                voice: None,
+            })
             .map_err(Error::say_not_installed)
     }
 
+    /// Lists the voices that `say` offers, as in `say -v ?`.
+    pub fn voices(&self) -> Result<Vec<VoiceInfo>, Error> {
+        let mut say = Command::new("say")
+            .arg("-v")
+            .arg("?")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(Error::cannot_invoke)?;
+        let stderr = StderrReader::start(&mut say);
+        let output = say.wait_with_output().map_err(Error::cannot_invoke)?;
+        if !output.status.success() {
+            return Err(Error::cannot_list_voices(output.status, stderr.finish()));
+        }
+        Ok(parse_say_voices(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Speaks with the voice of the given name, like `Anna`.
+    ///
+    /// Fails if `say` knows no such voice, instead of falling
+    /// back to the default voice.
+    pub fn with_voice(mut self, voice: &str) -> Result<Say, Error> {
+        if !self.voices()?.iter().any(|listed| listed.id == voice) {
+            return Err(Error::unknown_voice(voice));
+        }
+        self.voice = Some(voice.to_string());
+        Ok(self)
+    }
+
+    /// The name of the voice passed to `say`, or `None` if it
+    /// uses the system voice.
+    pub fn voice(&self) -> Option<&str> {
+        self.voice.as_deref()
+    }
+
     fn spawn(&self, output_file: Option<&Path>) -> Result<Child, Error> {
         let mut cmd = Command::new("say");
 
         cmd.stdin(Stdio::piped())
             .stdout(Stdio::null()) // Ignore standard output
-            .stderr(Stdio::null()); // And error too
+            .stderr(Stdio::piped()); // But keep errors for diagnostics
 
+        if let Some(voice) = &self.voice {
+            cmd.arg("-v");
+            cmd.arg(voice);
+        }
         if let Some(output) = output_file {
             cmd.arg("--data-format=LEF32@22050");
             cmd.arg("-o");
@@ -38,34 +92,35 @@ impl Say {
         cmd.spawn().map_err(Error::cannot_invoke)
     }
 
-    fn speak(&self, sentence: &str, output_file: Option<&Path>) -> Result<Speech, Error> {
+    fn speak(&self, utterance: &Utterance, output_file: Option<&Path>) -> Result<Speech, Error> {
+        let markup = render::say::render(
+            self.settings.prepare(utterance).tokens(),
+            &self.settings.pauses,
+        );
+        self.speak_markup(&markup, output_file)
+    }
+
+    fn speak_markup(&self, markup: &str, output_file: Option<&Path>) -> Result<Speech, Error> {
         let mut say = self.spawn(output_file)?;
-        let pipe = say.stdin.take().ok_or_else(Error::cannot_open_pipe)?;
-
-        self.write_say_markup(sentence, pipe)?;
-
-        Ok(Speech::new(say))
-    }
-
-    fn write_say_markup(&self, sentence: &str, mut pipe: ChildStdin) -> Result<(), Error> {
-        for token in Tokenizer::new(sentence) {
-            match token {
-                Token::Normal(text) => write!(pipe, " {}", text).map_err(Error::cannot_write)?,
-                Token::Emphasised(text) => {
-                    write!(pipe, "[[emph +]] {}[[emph -]]", text).map_err(Error::cannot_write)?
-                }
-                Token::Pause(Sentence) => {
-                    write!(pipe, "[[slnc 350]]").map_err(Error::cannot_write)?
-                }
-                Token::Pause(Paragraph) => {
-                    write!(pipe, "[[slnc 700]]").map_err(Error::cannot_write)?
-                }
-                Token::Pause(Seconds(secs)) => {
-                    write!(pipe, "[[slnc {}000]]", secs).map_err(Error::cannot_write)?
-                }
-            }
+        let stderr = StderrReader::start(&mut say);
+        let written = say
+            .stdin
+            .take()
+            .ok_or_else(Error::cannot_open_pipe)
+            .and_then(|pipe| self.write_say_markup(markup, pipe));
+
+        if let Err(err) = written {
+            // Most likely say exited early, e.g. on an unknown voice
+            let _ = say.kill();
+            let _ = say.wait();
+            return Err(err.with_stderr(stderr.finish()));
         }
-        writeln!(pipe).map_err(Error::cannot_write)?;
+
+        Ok(Speech::with_stderr(say, stderr))
+    }
+
+    fn write_say_markup(&self, markup: &str, mut pipe: ChildStdin) -> Result<(), Error> {
+        writeln!(pipe, "{}", markup).map_err(Error::cannot_write)?;
         pipe.flush().map_err(Error::cannot_write)
     }
 }
@@ -74,31 +129,37 @@ impl crate::Voice for Say {
     type Speech = Speech;
     type Error = Error;
 
-    /// Speaks the given sentence. Emphasized words can be wrapped in underscores.
-    fn speak<S>(&self, sentence: S) -> Result<Self::Speech, Self::Error>
-    where
-        S: AsRef<str>,
-    {
-        self.speak(sentence.as_ref(), None)
+    fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    fn speak_utterance(&self, utterance: &Utterance) -> Result<Self::Speech, Self::Error> {
+        self.speak(utterance, None)
     }
 
-    fn speak_to_file<S, P>(
+    fn speak_utterance_to_file<P>(
         &self,
-        sentence: S,
+        utterance: &Utterance,
         wav_file_path: P,
     ) -> Result<Self::Speech, Self::Error>
     where
-        S: AsRef<str>,
         P: AsRef<Path>,
     {
-        self.speak(sentence.as_ref(), Some(wav_file_path.as_ref()))
+        self.speak(utterance, Some(wav_file_path.as_ref()))
     }
 }
 
 mod err {
+    use crate::child::Stderr;
+    use crate::ssml::Error as SsmlError;
     use crate::version::Error as VersionDetectError;
     use failure::{Backtrace, Fail};
     use std::io::Error as IoError;
+    use std::process::ExitStatus;
 
     #[derive(Debug, Fail)]
     pub enum Error {
@@ -110,14 +171,31 @@ mod err {
             cause: IoError,
             backtrace: Backtrace,
         },
-        #[fail(display = "say command could not be written to: {}", cause)]
+        #[fail(
+            display = "say command could not be written to: {}, stderr: {}",
+            cause, stderr
+        )]
         CannotWrite {
             #[cause]
             cause: IoError,
+            stderr: Stderr,
             backtrace: Backtrace,
         },
         #[fail(display = "cannot open pipe to say")]
         CannotOpenPipe(Backtrace),
+        #[fail(display = "invalid SSML: {}", _0)]
+        InvalidSsml(#[cause] SsmlError),
+        #[fail(
+            display = "voices of say could not be listed: {}, stderr: {}",
+            status, stderr
+        )]
+        CannotListVoices {
+            status: ExitStatus,
+            stderr: Stderr,
+            backtrace: Backtrace,
+        },
+        #[fail(display = "say has no voice {:?}", voice)]
+        UnknownVoice { voice: String, backtrace: Backtrace },
     }
 
     impl Error {
@@ -135,13 +213,54 @@ mod err {
         pub fn cannot_write(cause: IoError) -> Self {
             Error::CannotWrite {
                 cause,
+                stderr: Stderr::default(),
                 backtrace: Backtrace::new(),
             }
         }
 
+        /// Attaches what `say` wrote to stderr to errors that
+        /// occur while it is running.
+        pub fn with_stderr(self, stderr: Stderr) -> Self {
+            match self {
+                Error::CannotWrite {
+                    cause, backtrace, ..
+                } => Error::CannotWrite {
+                    cause,
+                    stderr,
+                    backtrace,
+                },
+                other => other,
+            }
+        }
+
         pub fn cannot_open_pipe() -> Self {
             Error::CannotOpenPipe(Backtrace::new())
         }
+
+        pub fn invalid_ssml(cause: SsmlError) -> Self {
+            Error::InvalidSsml(cause)
+        }
+
+        pub fn cannot_list_voices(status: ExitStatus, stderr: Stderr) -> Self {
+            Error::CannotListVoices {
+                status,
+                stderr,
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn unknown_voice(voice: &str) -> Self {
+            Error::UnknownVoice {
+                voice: voice.into(),
+                backtrace: Backtrace::new(),
+            }
+        }
+    }
+
+    impl From<SsmlError> for Error {
+        fn from(cause: SsmlError) -> Self {
+            Error::invalid_ssml(cause)
+        }
     }
 }
 
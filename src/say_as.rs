@@ -0,0 +1,219 @@
+//! Hints on how to read text like ticket IDs, dates or phone
+//! numbers, and their expansion into plain words for backends
+//! that cannot interpret them natively.
+
+/// How the text of a [`Token::SayAs`](enum.Token.html) is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpretation {
+    /// Spelled out character by character, like `{chars: INC4711}`.
+    Characters,
+    /// Read digit by digit, like `{digits: 4711}`.
+    Digits,
+    /// A date like `{date: 2024-03-05}` or `{date: 5.3.2024}`.
+    Date,
+    /// A time of day like `{time: 14:05}`.
+    Time,
+    /// A telephone number like `{tel: +49 30 1234-56}`.
+    Telephone,
+    /// Spelled with the NATO phonetic alphabet, like `{nato: db1}`.
+    Nato,
+}
+
+const MONTHS: &[&str] = &[
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const NATO_LETTERS: &[&str] = &[
+    "Alfa", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel", "India", "Juliett",
+    "Kilo", "Lima", "Mike", "November", "Oscar", "Papa", "Quebec", "Romeo", "Sierra", "Tango",
+    "Uniform", "Victor", "Whiskey", "X-ray", "Yankee", "Zulu",
+];
+
+const NATO_DIGITS: &[&str] = &[
+    "Zero", "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Niner",
+];
+
+impl Interpretation {
+    /// Parses the keyword of a span like `{chars: ...}`.
+    pub(crate) fn from_keyword(keyword: &str) -> Option<Interpretation> {
+        match keyword {
+            "chars" => Some(Interpretation::Characters),
+            "digits" => Some(Interpretation::Digits),
+            "date" => Some(Interpretation::Date),
+            "time" => Some(Interpretation::Time),
+            "tel" => Some(Interpretation::Telephone),
+            "nato" => Some(Interpretation::Nato),
+            _ => None,
+        }
+    }
+
+    /// Expands the text into words that read the same with any
+    /// backend, used where a backend has no native equivalent.
+    ///
+    /// Dates and times that cannot be parsed are returned as they
+    /// are.
+    pub fn expand(self, text: &str) -> String {
+        let text = text.trim();
+        match self {
+            Interpretation::Characters | Interpretation::Digits => {
+                spell(text.chars(), |c| c.to_string())
+            }
+            Interpretation::Date => expand_date(text).unwrap_or_else(|| text.to_string()),
+            Interpretation::Time => expand_time(text).unwrap_or_else(|| text.to_string()),
+            Interpretation::Telephone => expand_telephone(text),
+            Interpretation::Nato => spell(text.chars(), nato_word),
+        }
+    }
+}
+
+/// Names each character with the given function, skipping
+/// whitespace and separating names with spaces.
+fn spell<C, F>(chars: C, name: F) -> String
+where
+    C: IntoIterator<Item = char>,
+    F: Fn(char) -> String,
+{
+    chars
+        .into_iter()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| symbol_name(c).map_or_else(|| name(c), String::from))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn symbol_name(c: char) -> Option<&'static str> {
+    match c {
+        '-' => Some("dash"),
+        '.' => Some("dot"),
+        '_' => Some("underscore"),
+        '/' => Some("slash"),
+        ':' => Some("colon"),
+        '@' => Some("at"),
+        '#' => Some("hash"),
+        '+' => Some("plus"),
+        _ => None,
+    }
+}
+
+fn nato_word(c: char) -> String {
+    let c = c.to_ascii_lowercase();
+    if c.is_ascii_lowercase() {
+        NATO_LETTERS[(c as u8 - b'a') as usize].to_string()
+    } else if c.is_ascii_digit() {
+        NATO_DIGITS[(c as u8 - b'0') as usize].to_string()
+    } else {
+        c.to_string()
+    }
+}
+
+/// Reads ISO dates like `2024-03-05` and dotted dates like
+/// `5.3.2024` as `March 5, 2024`.
+fn expand_date(text: &str) -> Option<String> {
+    let parts = text
+        .split(['-', '.'])
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    let (year, month, day) = match (parts.as_slice(), text.contains('-')) {
+        ([year, month, day], true) => (*year, *month, *day),
+        ([day, month, year], false) => (*year, *month, *day),
+        _ => return None,
+    };
+
+    if !(1..=31).contains(&day) {
+        return None;
+    }
+    let month = MONTHS.get(month.checked_sub(1)? as usize)?;
+    Some(format!("{} {}, {}", month, day, year))
+}
+
+/// Reads times like `14:05` as `14 oh 5` and full hours like
+/// `9:00` as `9 o'clock`. Seconds are dropped.
+fn expand_time(text: &str) -> Option<String> {
+    let mut parts = text.split(':');
+    let hours: u32 = parts.next()?.parse().ok()?;
+    let minutes: u32 = parts.next()?.parse().ok()?;
+    let seconds_valid = parts
+        .next()
+        .is_none_or(|seconds| seconds.parse::<u32>().is_ok_and(|s| s < 60));
+
+    if hours > 23 || minutes > 59 || !seconds_valid || parts.next().is_some() {
+        return None;
+    }
+
+    Some(match minutes {
+        0 => format!("{} o'clock", hours),
+        1..=9 => format!("{} oh {}", hours, minutes),
+        _ => format!("{} {}", hours, minutes),
+    })
+}
+
+/// Reads every digit on its own, with a short break between
+/// the groups of the number.
+fn expand_telephone(text: &str) -> String {
+    text.split(|c: char| c.is_whitespace() || "-/().".contains(c))
+        .filter(|group| !group.is_empty())
+        .map(|group| spell(group.chars(), |c| c.to_string()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn characters_and_digits() {
+        assert_eq!(
+            Interpretation::Characters.expand("INC-42"),
+            "I N C dash 4 2"
+        );
+        assert_eq!(Interpretation::Digits.expand(" 4 711 "), "4 7 1 1");
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(Interpretation::Date.expand("2024-03-05"), "March 5, 2024");
+        assert_eq!(
+            Interpretation::Date.expand("24.12.2023"),
+            "December 24, 2023"
+        );
+        assert_eq!(Interpretation::Date.expand("2024-13-05"), "2024-13-05");
+        assert_eq!(Interpretation::Date.expand("yesterday"), "yesterday");
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(Interpretation::Time.expand("14:05"), "14 oh 5");
+        assert_eq!(Interpretation::Time.expand("9:00:30"), "9 o'clock");
+        assert_eq!(Interpretation::Time.expand("23:45"), "23 45");
+        assert_eq!(Interpretation::Time.expand("25:00"), "25:00");
+    }
+
+    #[test]
+    fn telephone_numbers() {
+        assert_eq!(
+            Interpretation::Telephone.expand("+49 (30) 123-45"),
+            "plus 4 9, 3 0, 1 2 3, 4 5"
+        );
+    }
+
+    #[test]
+    fn nato_alphabet() {
+        assert_eq!(
+            Interpretation::Nato.expand("db-9x"),
+            "Delta Bravo dash Niner X-ray"
+        );
+    }
+}
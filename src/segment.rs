@@ -0,0 +1,215 @@
+//! Splitting utterances into sentences and paragraphs, so that
+//! long texts can be spoken one segment at a time.
+
+use crate::token::{is_abbreviation, last_word, PauseDuration, Prosody, Token};
+use crate::utterance::Utterance;
+use std::borrow::Cow;
+
+/// What ends a [`Segment`](struct.Segment.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    /// The end of a sentence, like a dot or a question mark.
+    Sentence,
+    /// The end of a paragraph, like a blank line.
+    Paragraph,
+    /// The end of the utterance.
+    End,
+}
+
+/// A sentence or paragraph of an utterance, with the pause
+/// that ends it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub utterance: Utterance,
+    pub boundary: Boundary,
+}
+
+/// Splits tokens at pauses that end sentences and paragraphs,
+/// and text at sentence ending punctuation like `?` or `!`.
+///
+/// Prosody spans that cross a boundary are closed at the end
+/// of the segment and opened again in the next one, so every
+/// segment can be spoken on its own.
+pub(crate) fn segments(tokens: &[Token<'static>]) -> Vec<Segment> {
+    let mut segmenter = Segmenter::default();
+
+    for token in tokens {
+        match token {
+            Token::Pause(pause) => {
+                segmenter.push(token.clone());
+                match pause {
+                    PauseDuration::Sentence | PauseDuration::DotRun(_) => {
+                        segmenter.finish(Boundary::Sentence)
+                    }
+                    PauseDuration::Paragraph => segmenter.finish(Boundary::Paragraph),
+                    PauseDuration::Line | PauseDuration::Exact(_) => (),
+                }
+            }
+            Token::ProsodyStart(prosody) => {
+                segmenter.open.push(*prosody);
+                segmenter.push(token.clone());
+            }
+            Token::ProsodyEnd(prosody) => {
+                if let Some(idx) = segmenter.open.iter().rposition(|open| open == prosody) {
+                    segmenter.open.remove(idx);
+                }
+                segmenter.push(token.clone());
+            }
+            Token::Normal(text)
+            | Token::Emphasised(text)
+            | Token::Strong(text)
+            | Token::Reduced(text) => {
+                let mut rest: &str = text;
+                while let Some(end) = sentence_end(rest) {
+                    let (sentence, after) = rest.split_at(end);
+                    segmenter.push(with_text(token, sentence));
+                    segmenter.finish(Boundary::Sentence);
+                    rest = after.trim_start();
+                }
+                if !rest.is_empty() {
+                    segmenter.push(with_text(token, rest));
+                }
+            }
+            Token::SayAs(..) | Token::Phoneme { .. } | Token::Mark(_) => {
+                segmenter.push(token.clone())
+            }
+        }
+    }
+
+    segmenter.finish(Boundary::End);
+    segmenter.segments
+}
+
+#[derive(Default)]
+struct Segmenter {
+    segments: Vec<Segment>,
+    current: Vec<Token<'static>>,
+    /// Prosody spans open at the current position.
+    open: Vec<Prosody>,
+}
+
+impl Segmenter {
+    fn push(&mut self, token: Token<'static>) {
+        self.current.push(token);
+    }
+
+    fn finish(&mut self, boundary: Boundary) {
+        let is_spoken = self.current.iter().any(Token::is_spoken);
+
+        if !is_spoken {
+            // Pauses after a boundary belong to the segment before,
+            // a blank line after a sentence makes it a paragraph
+            if let Some(last) = self.segments.last_mut() {
+                if self.open.is_empty() {
+                    last.utterance.extend(self.current.drain(..));
+                    if boundary == Boundary::Paragraph {
+                        last.boundary = Boundary::Paragraph;
+                    }
+                }
+            } else if boundary == Boundary::End && !self.current.is_empty() {
+                // Nothing but pauses, still speak them
+                self.segments.push(Segment {
+                    utterance: self.current.drain(..).collect(),
+                    boundary,
+                });
+            }
+            return;
+        }
+
+        let mut tokens: Vec<Token<'static>> = self.current.drain(..).collect();
+        tokens.extend(
+            self.open
+                .iter()
+                .rev()
+                .map(|&prosody| Token::ProsodyEnd(prosody)),
+        );
+        self.current.extend(
+            self.open
+                .iter()
+                .map(|&prosody| Token::ProsodyStart(prosody)),
+        );
+
+        self.segments.push(Segment {
+            utterance: tokens.into(),
+            boundary,
+        });
+    }
+}
+
+/// Finds the end of the first sentence in the text, after
+/// punctuation that is followed by whitespace or ends the text.
+///
+/// Dots only end sentences if they do not belong to an
+/// abbreviation, like in text from SSML or escaped dots.
+fn sentence_end(text: &str) -> Option<usize> {
+    text.char_indices().find_map(|(idx, c)| {
+        let end = idx + c.len_utf8();
+        let is_end = match c {
+            '?' | '!' | '…' => true,
+            '.' => !is_abbreviation(last_word(&text[..idx])),
+            _ => false,
+        };
+        let is_followed_by_space = text[end..].chars().next().is_none_or(char::is_whitespace);
+        (is_end && is_followed_by_space).then_some(end)
+    })
+}
+
+/// A copy of a text token with other text, but the same style.
+fn with_text(token: &Token<'static>, text: &str) -> Token<'static> {
+    token.clone().map_text(|_| Cow::Owned(text.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn texts(segment: &Segment) -> Vec<&str> {
+        segment
+            .utterance
+            .tokens()
+            .iter()
+            .filter_map(|token| match token {
+                Token::Normal(text) | Token::Emphasised(text) => Some(text.as_ref()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sentences_and_paragraphs() {
+        let segments =
+            Utterance::parse("Build failed. Why? _Nobody_ knows\n\nRetry, e.g. now").segments();
+
+        let boundaries: Vec<_> = segments.iter().map(|s| s.boundary).collect();
+        assert_eq!(
+            boundaries,
+            vec![
+                Boundary::Sentence,
+                Boundary::Sentence,
+                Boundary::Paragraph,
+                Boundary::End
+            ]
+        );
+        assert_eq!(texts(&segments[1]), vec!["Why?"]);
+        assert_eq!(texts(&segments[2]), vec!["Nobody", "knows"]);
+        assert_eq!(texts(&segments[3]), vec!["Retry, e.g. now"]);
+    }
+
+    #[test]
+    fn prosody_across_boundaries() {
+        let segments = Utterance::parse("{slow: One. Two}").segments();
+        assert_eq!(segments.len(), 2);
+        for segment in &segments {
+            let tokens = segment.utterance.tokens();
+            assert_eq!(tokens.first(), Some(&Token::ProsodyStart(Prosody::Slow)));
+            assert_eq!(tokens.last(), Some(&Token::ProsodyEnd(Prosody::Slow)));
+        }
+    }
+
+    #[test]
+    fn only_pauses() {
+        let segments = Utterance::parse(".").segments();
+        assert_eq!(segments.len(), 1);
+        assert!(Utterance::new().segments().is_empty());
+    }
+}
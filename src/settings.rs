@@ -0,0 +1,126 @@
+//! Settings shared by all voices that tune how utterances
+//! are spoken, independent of the backend.
+
+use crate::lexicon::Lexicon;
+use crate::normalize::Language;
+use crate::symbols::SymbolVerbosity;
+use crate::token::{PauseDuration, Prosody};
+use crate::utterance::Utterance;
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// Settings of a [`Voice`](trait.Voice.html), obtained with
+/// `settings` and changed with `settings_mut`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Settings {
+    /// The lengths of the pauses written with dots and newlines.
+    pub pauses: PauseProfile,
+    /// Lexicons applied to every utterance before speaking, in
+    /// order, and before normalization.
+    pub lexicons: Vec<Lexicon>,
+    /// Whether emoji and symbols are kept, removed or spoken by
+    /// name. Kept by default.
+    pub symbols: SymbolVerbosity,
+    /// The language to expand numbers, dates and abbreviations
+    /// in before speaking, or `None` to leave them to the
+    /// backend, which is the default.
+    pub normalization: Option<Language>,
+    /// Prosody that every utterance is spoken with, outermost
+    /// first, like a span around all of it. Empty by default.
+    pub prosody: Vec<Prosody>,
+}
+
+impl Settings {
+    /// The utterance as it should be rendered with these settings.
+    pub(crate) fn prepare<'u>(&self, utterance: &'u Utterance) -> Cow<'u, Utterance> {
+        if self.lexicons.is_empty()
+            && self.symbols == SymbolVerbosity::Keep
+            && self.normalization.is_none()
+            && self.prosody.is_empty()
+        {
+            return Cow::Borrowed(utterance);
+        }
+
+        let mut prepared = utterance.clone();
+        for lexicon in &self.lexicons {
+            prepared = lexicon.apply(prepared);
+        }
+        prepared = prepared.verbalize_symbols(self.symbols);
+        if let Some(language) = self.normalization {
+            prepared = prepared.normalize(language);
+        }
+        for &prosody in self.prosody.iter().rev() {
+            prepared = Utterance::new().prosody(prosody, |_| prepared);
+        }
+        Cow::Owned(prepared)
+    }
+}
+
+/// The lengths of line, sentence, paragraph and dot-run pauses, so
+/// that pacing is the same with every backend.
+///
+/// Explicit pauses like `{pause 250ms}` are not affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PauseProfile {
+    /// The pause at the end of a line read as verse, 200ms by
+    /// default.
+    pub line: Duration,
+    /// The pause of a single dot, 350ms by default.
+    pub sentence: Duration,
+    /// The pause of two dots or a blank line, 700ms by default.
+    pub paragraph: Duration,
+    /// The pause for each dot of a run beyond the second, one
+    /// second by default.
+    pub per_extra_dot: Duration,
+}
+
+impl PauseProfile {
+    /// Shorter pauses than the default, for notifications that
+    /// should not take long.
+    pub fn brisk() -> PauseProfile {
+        PauseProfile {
+            line: Duration::from_millis(100),
+            sentence: Duration::from_millis(200),
+            paragraph: Duration::from_millis(400),
+            per_extra_dot: Duration::from_millis(500),
+        }
+    }
+
+    /// The length of the given pause in this profile.
+    pub fn duration(&self, pause: PauseDuration) -> Duration {
+        match pause {
+            PauseDuration::Line => self.line,
+            PauseDuration::Sentence => self.sentence,
+            PauseDuration::Paragraph => self.paragraph,
+            PauseDuration::DotRun(extra_dots) => self.per_extra_dot * extra_dots,
+            PauseDuration::Exact(duration) => duration,
+        }
+    }
+}
+
+impl Default for PauseProfile {
+    fn default() -> Self {
+        PauseProfile {
+            line: Duration::from_millis(200),
+            sentence: Duration::from_millis(350),
+            paragraph: Duration::from_millis(700),
+            per_extra_dot: Duration::from_secs(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_durations() {
+        let profile = PauseProfile::default();
+        assert_eq!(profile.duration(PauseDuration::Sentence).as_millis(), 350);
+        assert_eq!(profile.duration(PauseDuration::DotRun(3)).as_millis(), 3000);
+        assert_eq!(
+            profile.duration(PauseDuration::Exact(Duration::from_millis(42))),
+            Duration::from_millis(42)
+        );
+    }
+}
@@ -0,0 +1,515 @@
+//! Validation of SSML documents and their translation
+//! into tokens, so that backends without SSML support
+//! can render them into their own markup.
+//!
+//! Elements without an equivalent token degrade to the
+//! plain text they contain.
+
+pub use err::Error;
+
+use crate::phoneme::PhoneticAlphabet;
+use crate::say_as::Interpretation;
+use crate::token::{parse_duration, PauseDuration, Prosody, Token};
+use roxmltree::{Document, Node};
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Parses and validates the given SSML document, translating
+/// it into tokens.
+pub fn parse(source: &str) -> Result<Vec<Token<'static>>, Error> {
+    let document = Document::parse(source).map_err(Error::malformed)?;
+    let root = document.root_element();
+
+    if root.tag_name().name() != "speak" {
+        let pos = document.text_pos_at(root.range().start);
+        return Err(Error::not_speak(pos.row, pos.col, root.tag_name().name()));
+    }
+
+    let mut translation = Translation {
+        document: &document,
+        tokens: Vec::new(),
+        emphasis: Vec::new(),
+    };
+    translation.element(root)?;
+
+    Ok(translation.tokens)
+}
+
+/// A prosody attribute and the keywords that lower or raise it.
+struct ProsodyProperty {
+    attribute: &'static str,
+    lower: &'static [&'static str],
+    higher: &'static [&'static str],
+    lowered: Prosody,
+    raised: Prosody,
+}
+
+const PROSODY_PROPERTIES: &[ProsodyProperty] = &[
+    ProsodyProperty {
+        attribute: "rate",
+        lower: &["x-slow", "slow"],
+        higher: &["fast", "x-fast"],
+        lowered: Prosody::Slow,
+        raised: Prosody::Fast,
+    },
+    ProsodyProperty {
+        attribute: "pitch",
+        lower: &["x-low", "low"],
+        higher: &["high", "x-high"],
+        lowered: Prosody::Low,
+        raised: Prosody::High,
+    },
+    ProsodyProperty {
+        attribute: "volume",
+        lower: &["silent", "x-soft", "soft"],
+        higher: &["loud", "x-loud"],
+        lowered: Prosody::Soft,
+        raised: Prosody::Loud,
+    },
+];
+
+struct Translation<'d, 'input> {
+    document: &'d Document<'input>,
+    tokens: Vec<Token<'static>>,
+    /// Makes text tokens for the open `<emphasis>` elements,
+    /// innermost last.
+    emphasis: Vec<fn(Cow<'static, str>) -> Token<'static>>,
+}
+
+impl<'d, 'input> Translation<'d, 'input> {
+    fn element(&mut self, node: Node) -> Result<(), Error> {
+        match node.tag_name().name() {
+            "break" => {
+                if let Some(pause) = self.break_pause(node)? {
+                    self.tokens.push(Token::Pause(pause));
+                }
+            }
+            "emphasis" => {
+                let token = match node.attribute("level") {
+                    Some("none") => Token::Normal,
+                    Some("reduced") => Token::Reduced,
+                    Some("moderate") | None => Token::Emphasised,
+                    Some("strong") => Token::Strong,
+                    Some(other) => return Err(self.invalid_attribute(node, "level", other)),
+                };
+
+                self.emphasis.push(token);
+                self.children(node)?;
+                self.emphasis.pop();
+            }
+            "prosody" => {
+                let spans = self.prosody_spans(node)?;
+                self.tokens
+                    .extend(spans.iter().cloned().map(Token::ProsodyStart));
+                self.children(node)?;
+                self.tokens
+                    .extend(spans.iter().rev().cloned().map(Token::ProsodyEnd));
+            }
+            "say-as" => match node.attribute("interpret-as").and_then(interpretation) {
+                Some(interpretation) => {
+                    let text = node_text(node);
+                    if !text.is_empty() {
+                        self.tokens
+                            .push(Token::SayAs(interpretation, Cow::Owned(text)));
+                    }
+                }
+                None => self.children(node)?,
+            },
+            "phoneme" => match (node.attribute("alphabet"), node.attribute("ph")) {
+                (Some("ipa"), Some(phonemes)) => self.tokens.push(Token::Phoneme {
+                    alphabet: PhoneticAlphabet::Ipa,
+                    phonemes: Cow::Owned(phonemes.trim().to_string()),
+                    text: Cow::Owned(node_text(node)),
+                }),
+                // Vendor specific alphabets, speak the text
+                _ => self.children(node)?,
+            },
+            // Marks rendered by tavla itself name a source range
+            "mark" => {
+                if let Some(span) = node.attribute("name").and_then(mark_span) {
+                    self.tokens.push(Token::Mark(span));
+                }
+            }
+            "p" | "paragraph" => {
+                self.children(node)?;
+                self.end_with(PauseDuration::Paragraph);
+            }
+            "s" | "sentence" => {
+                self.children(node)?;
+                self.end_with(PauseDuration::Sentence);
+            }
+            // speak and elements we cannot translate, speak their text
+            _ => self.children(node)?,
+        }
+
+        Ok(())
+    }
+
+    fn children(&mut self, node: Node) -> Result<(), Error> {
+        for child in node.children() {
+            if child.is_element() {
+                self.element(child)?;
+            } else if let Some(text) = child.text().filter(|_| child.is_text()) {
+                self.text(text);
+            }
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str) {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            let text = Cow::Owned(text);
+            let token = self.emphasis.last().copied().unwrap_or(Token::Normal);
+            self.tokens.push(token(text));
+        }
+    }
+
+    /// Ends a sentence or paragraph, unless directly following
+    /// a pause that is at least as long.
+    fn end_with(&mut self, pause: PauseDuration) {
+        match (self.tokens.last(), &pause) {
+            (Some(Token::Pause(PauseDuration::Sentence)), PauseDuration::Paragraph) => {
+                self.tokens.pop();
+            }
+            (Some(Token::Pause(_)), _) | (None, _) => return,
+            _ => (),
+        }
+        self.tokens.push(Token::Pause(pause));
+    }
+
+    fn break_pause(&self, node: Node) -> Result<Option<PauseDuration>, Error> {
+        if let Some(time) = node.attribute("time") {
+            let duration =
+                parse_duration(time).ok_or_else(|| self.invalid_attribute(node, "time", time))?;
+            return Ok(if duration.as_millis() == 0 {
+                None
+            } else {
+                Some(PauseDuration::Exact(duration))
+            });
+        }
+
+        match node.attribute("strength") {
+            Some("none") | Some("x-weak") => Ok(None),
+            Some("weak") | Some("medium") | None => Ok(Some(PauseDuration::Sentence)),
+            Some("strong") | Some("x-strong") => Ok(Some(PauseDuration::Paragraph)),
+            Some(other) => Err(self.invalid_attribute(node, "strength", other)),
+        }
+    }
+
+    fn prosody_spans(&self, node: Node) -> Result<Vec<Prosody>, Error> {
+        let mut spans = Vec::new();
+        for property in PROSODY_PROPERTIES {
+            if let Some(value) = node.attribute(property.attribute) {
+                let direction = direction(
+                    value,
+                    property.lower,
+                    property.higher,
+                    property.attribute == "rate",
+                )
+                .ok_or_else(|| self.invalid_attribute(node, property.attribute, value))?;
+                match direction {
+                    Some(true) => spans.push(property.raised),
+                    Some(false) => spans.push(property.lowered),
+                    None => (),
+                }
+            }
+        }
+        Ok(spans)
+    }
+
+    fn invalid_attribute(&self, node: Node, attribute: &str, value: &str) -> Error {
+        let range = node
+            .attributes()
+            .find(|a| a.name() == attribute)
+            .map(|a| a.range())
+            .unwrap_or_else(|| node.range());
+        let pos = self.document.text_pos_at(range.start);
+        Error::invalid_attribute(pos.row, pos.col, node.tag_name().name(), attribute, value)
+    }
+}
+
+/// The text inside of the node, with whitespace collapsed.
+pub(crate) fn node_text(node: Node) -> String {
+    let text = node
+        .descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<String>();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Parses mark names like `12-34` into a source range.
+fn mark_span(name: &str) -> Option<Range<usize>> {
+    let (start, end) = name.split_once('-')?;
+    Some(start.parse().ok()?..end.parse().ok()?)
+}
+
+/// Maps the `interpret-as` values with an equivalent token,
+/// including the `tts:` extensions of espeak.
+fn interpretation(interpret_as: &str) -> Option<Interpretation> {
+    match interpret_as {
+        "characters" | "spell-out" | "tts:char" => Some(Interpretation::Characters),
+        "digits" | "tts:digits" => Some(Interpretation::Digits),
+        "date" => Some(Interpretation::Date),
+        "time" => Some(Interpretation::Time),
+        "telephone" => Some(Interpretation::Telephone),
+        _ => None,
+    }
+}
+
+/// Interprets a prosody attribute value as raising (`true`)
+/// or lowering (`false`) the property, or `None` for values
+/// without a clear direction like `medium` or absolute pitches.
+///
+/// Returns `None` for values that are not valid at all.
+fn direction(
+    value: &str,
+    lower: &[&str],
+    higher: &[&str],
+    multiplier: bool,
+) -> Option<Option<bool>> {
+    let value = value.trim();
+    if lower.contains(&value) {
+        return Some(Some(false));
+    } else if higher.contains(&value) {
+        return Some(Some(true));
+    } else if value == "medium" || value == "default" {
+        return Some(None);
+    }
+
+    let number_end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '+' || c == '-'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(number_end);
+    let number: f64 = number.parse().ok()?;
+    if !number.is_finite() || !["", "%", "Hz", "st", "dB"].contains(&unit) {
+        return None;
+    }
+
+    let compare = |baseline: f64| {
+        if number > baseline {
+            Some(true)
+        } else if number < baseline {
+            Some(false)
+        } else {
+            None
+        }
+    };
+
+    Some(if value.starts_with('+') || value.starts_with('-') {
+        compare(0.0)
+    } else if unit == "%" {
+        compare(100.0)
+    } else if unit.is_empty() && multiplier {
+        compare(1.0)
+    } else {
+        // absolute values depend on the voice, keep as is
+        None
+    })
+}
+
+mod err {
+    use failure::{Backtrace, Fail};
+
+    /// An SSML document that could not be parsed or uses
+    /// invalid values for the elements tavla understands.
+    ///
+    /// Lines and columns start at one.
+    #[derive(Fail, Debug)]
+    pub enum Error {
+        #[fail(display = "malformed SSML: {}", cause)]
+        Malformed {
+            line: u32,
+            column: u32,
+            #[cause]
+            cause: roxmltree::Error,
+            backtrace: Backtrace,
+        },
+        #[fail(
+            display = "SSML root element must be <speak>, but found <{}> at {}:{}",
+            element, line, column
+        )]
+        NotSpeak {
+            line: u32,
+            column: u32,
+            element: String,
+            backtrace: Backtrace,
+        },
+        #[fail(
+            display = "invalid value {:?} for attribute {} of <{}> at {}:{}",
+            value, attribute, element, line, column
+        )]
+        InvalidAttribute {
+            line: u32,
+            column: u32,
+            element: String,
+            attribute: String,
+            value: String,
+            backtrace: Backtrace,
+        },
+    }
+
+    impl Error {
+        pub fn malformed(cause: roxmltree::Error) -> Self {
+            let pos = cause.pos();
+            Error::Malformed {
+                line: pos.row,
+                column: pos.col,
+                cause,
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn not_speak(line: u32, column: u32, element: &str) -> Self {
+            Error::NotSpeak {
+                line,
+                column,
+                element: element.into(),
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        pub fn invalid_attribute(
+            line: u32,
+            column: u32,
+            element: &str,
+            attribute: &str,
+            value: &str,
+        ) -> Self {
+            Error::InvalidAttribute {
+                line,
+                column,
+                element: element.into(),
+                attribute: attribute.into(),
+                value: value.into(),
+                backtrace: Backtrace::new(),
+            }
+        }
+
+        /// Line and column where the problem was found.
+        pub fn position(&self) -> (u32, u32) {
+            match self {
+                Error::Malformed { line, column, .. }
+                | Error::NotSpeak { line, column, .. }
+                | Error::InvalidAttribute { line, column, .. } => (*line, *column),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render;
+    use crate::settings::PauseProfile;
+    use crate::Utterance;
+    use std::time::Duration;
+
+    const DOCUMENT: &str = r#"<?xml version="1.0"?>
+<speak version="1.1" xmlns="http://www.w3.org/2001/10/synthesis">
+  <p>
+    <s>Disk <emphasis>full</emphasis> on &lt;db1&gt;</s>
+    <s><emphasis level="strong">Now</emphasis></s>
+    <s><prosody rate="slow" volume="+6dB">Act now</prosody></s>
+  </p>
+  <break time="2s"/>
+  Call <say-as interpret-as="telephone">555 0100</say-as><audio src="beep.wav"/>
+  <phoneme alphabet="ipa" ph="ˈtɑːvlə">tavla</phoneme>
+</speak>"#;
+
+    #[test]
+    fn translate_document() {
+        assert_eq!(
+            parse(DOCUMENT).unwrap(),
+            vec![
+                Token::Normal("Disk".into()),
+                Token::Emphasised("full".into()),
+                Token::Normal("on <db1>".into()),
+                Token::Pause(PauseDuration::Sentence),
+                Token::Strong("Now".into()),
+                Token::Pause(PauseDuration::Sentence),
+                Token::ProsodyStart(Prosody::Slow),
+                Token::ProsodyStart(Prosody::Loud),
+                Token::Normal("Act now".into()),
+                Token::ProsodyEnd(Prosody::Loud),
+                Token::ProsodyEnd(Prosody::Slow),
+                Token::Pause(PauseDuration::Paragraph),
+                Token::Pause(PauseDuration::Exact(Duration::from_secs(2))),
+                Token::Normal("Call".into()),
+                Token::SayAs(Interpretation::Telephone, "555 0100".into()),
+                Token::Phoneme {
+                    alphabet: PhoneticAlphabet::Ipa,
+                    phonemes: "ˈtɑːvlə".into(),
+                    text: "tavla".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn translate_to_say() {
+        assert_eq!(
+            render::say::render(&parse(DOCUMENT).unwrap(), &PauseProfile::default()),
+            " Disk[[emph +]] full[[emph -]] on <db1>[[slnc 350]]\
+             [[emph +]][[volm +0.2]] Now[[volm -0.2]][[emph -]][[slnc 350]][[rate -50]][[volm +0.3]] \
+             Act now[[volm -0.3]][[rate +50]][[slnc 700]][[slnc 2000]] Call 5 5 5, 0 1 0 0 \
+             [[inpt PHON]]t1AAvlAX[[inpt TEXT]]"
+        );
+    }
+
+    #[test]
+    fn translate_to_sapi() {
+        assert_eq!(
+            render::sapi::render(&parse(DOCUMENT).unwrap(), &PauseProfile::default()),
+            "<sapi> Disk<emph>full</emph> on &lt;db1&gt;<silence msec=\"350\" />\
+             <emph><pitch middle=\"2\">Now</pitch></emph><silence msec=\"350\" />\
+             <rate speed=\"-4\"><pitch middle=\"2\"> Act now</pitch></rate>\
+             <silence msec=\"700\" /><silence msec=\"2000\" /> Call 5 5 5, 0 1 0 0 \
+             <pron sym=\"t aa1 v l ax\"/></sapi>"
+        );
+    }
+
+    #[test]
+    fn marks_round_trip() {
+        let utterance = Utterance::parse_with_marks("Hello _there_");
+        let ssml = render::ssml::render(utterance.tokens(), &PauseProfile::default());
+        assert_eq!(parse(&ssml).unwrap(), utterance.into_tokens());
+    }
+
+    #[test]
+    fn malformed_position() {
+        let err = parse("<speak>\n  <s>unclosed\n</speak>").unwrap_err();
+        match err {
+            Error::Malformed { line, .. } => assert_eq!(line, 3),
+            other => panic!("Expected malformed error, got: {}", other),
+        }
+    }
+
+    #[test]
+    fn root_must_be_speak() {
+        let err = parse("\n <html>hi</html>").unwrap_err();
+        match err {
+            Error::NotSpeak { ref element, .. } => assert_eq!(element, "html"),
+            ref other => panic!("Expected wrong root error, got: {}", other),
+        }
+        assert_eq!(err.position(), (2, 2));
+    }
+
+    #[test]
+    fn invalid_attribute_position() {
+        let err = parse("<speak>\nwait <break time=\"soon\"/></speak>").unwrap_err();
+        match err {
+            Error::InvalidAttribute {
+                ref attribute,
+                ref value,
+                ..
+            } => {
+                assert_eq!(attribute, "time");
+                assert_eq!(value, "soon");
+            }
+            ref other => panic!("Expected invalid attribute error, got: {}", other),
+        }
+        assert_eq!(err.position(), (2, 13));
+    }
+}
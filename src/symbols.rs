@@ -0,0 +1,196 @@
+//! Emoji, arrows, math symbols and box-drawing characters,
+//! which backends skip or read as byte soup, replaced with
+//! their spoken names or removed.
+
+use crate::token::Token;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Names of symbols after the CLDR short names, one per line
+/// with the symbol and name separated by a tab.
+const SYMBOL_NAMES: &str = include_str!("../resources/symbol_names.tsv");
+
+/// What happens to emoji and symbols in spoken text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymbolVerbosity {
+    /// Symbols are passed to the backend as they are.
+    #[default]
+    Keep,
+    /// Emoji and symbols are removed, so that they are not spoken.
+    Remove,
+    /// Emoji and symbols are spoken by name, like `rocket` or
+    /// `right arrow`. Decorations like box drawing and symbols
+    /// without a known name are removed.
+    ///
+    /// The names are English, regardless of the language of the
+    /// voice.
+    Names,
+}
+
+struct Names {
+    names: HashMap<&'static str, &'static str>,
+    /// The length of the longest symbol in chars, some emoji are
+    /// sequences joined with zero width joiners.
+    longest: usize,
+}
+
+fn names() -> &'static Names {
+    static NAMES: OnceLock<Names> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        let names: HashMap<_, _> = SYMBOL_NAMES
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('\t'))
+            .collect();
+        let longest = names.keys().map(|s| s.chars().count()).max().unwrap_or(1);
+        Names { names, longest }
+    })
+}
+
+/// Replaces or removes emoji and symbols in the text according
+/// to the verbosity. Borrows if nothing had to change.
+pub fn verbalize_symbols(text: &str, verbosity: SymbolVerbosity) -> Cow<'_, str> {
+    let is_affected = |c: char| {
+        is_symbol(c)
+            || is_decoration(c)
+            || is_regional_indicator(c)
+            || c == '\u{200D}'
+            || (verbosity == SymbolVerbosity::Names && longest_name(&[c]).is_some())
+    };
+    if verbosity == SymbolVerbosity::Keep || !text.chars().any(is_affected) {
+        return Cow::Borrowed(text);
+    }
+
+    // Skin tones and presentation selectors do not change names
+    let chars: Vec<char> = text.chars().filter(|&c| !is_decoration(c)).collect();
+    let mut verbalized = String::with_capacity(text.len());
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let c = chars[idx];
+        if verbosity == SymbolVerbosity::Names {
+            let spoken = longest_name(&chars[idx..])
+                .map(|(name, len)| (Cow::Borrowed(name), len))
+                .or_else(|| flag(&chars[idx..]).map(|flag| (Cow::Owned(flag), 2)));
+            if let Some((name, len)) = spoken {
+                idx += len;
+                verbalized.push(' ');
+                verbalized.push_str(&name);
+                // Punctuation after the symbol stays attached to the name
+                if !chars.get(idx).is_some_and(char::is_ascii_punctuation) {
+                    verbalized.push(' ');
+                }
+                continue;
+            }
+        }
+
+        if is_symbol(c) || is_regional_indicator(c) || c == '\u{200D}' {
+            verbalized.push(' ');
+        } else {
+            verbalized.push(c);
+        }
+        idx += 1;
+    }
+
+    Cow::Owned(verbalized.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Verbalizes the symbols in tokens of text, leaving say-as
+/// hints and phonemes as they are.
+pub(crate) fn verbalize_token(token: Token<'_>, verbosity: SymbolVerbosity) -> Token<'_> {
+    token.map_text(|text| match verbalize_symbols(&text, verbosity) {
+        Cow::Borrowed(_) => text,
+        Cow::Owned(verbalized) => Cow::Owned(verbalized),
+    })
+}
+
+/// The name of the longest known symbol at the start of the
+/// chars, along with its length in chars.
+fn longest_name(chars: &[char]) -> Option<(&'static str, usize)> {
+    let names = names();
+    (1..=names.longest.min(chars.len())).rev().find_map(|len| {
+        let symbol: String = chars[..len].iter().collect();
+        names.names.get(symbol.as_str()).map(|name| (*name, len))
+    })
+}
+
+/// Flags are pairs of regional indicators that spell a region
+/// code, read as `flag D E`.
+fn flag(chars: &[char]) -> Option<String> {
+    match chars {
+        [first, second, ..] if is_regional_indicator(*first) && is_regional_indicator(*second) => {
+            let letter = |c: char| char::from(b'A' + (c as u32 - 0x1F1E6) as u8);
+            Some(format!("flag {} {}", letter(*first), letter(*second)))
+        }
+        _ => None,
+    }
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+/// Characters that only change how the symbol before is shown,
+/// like variation selectors, skin tones and keycap frames.
+fn is_decoration(c: char) -> bool {
+    matches!(c, '\u{FE0E}' | '\u{FE0F}' | '\u{20E3}')
+        || ('\u{1F3FB}'..='\u{1F3FF}').contains(&c)
+        || ('\u{E0020}'..='\u{E007F}').contains(&c)
+}
+
+/// Arrows, math operators, technical symbols, box drawing,
+/// geometric shapes, dingbats and emoji.
+fn is_symbol(c: char) -> bool {
+    matches!(c,
+        '\u{2190}'..='\u{23FF}'
+        | '\u{2500}'..='\u{27BF}'
+        | '\u{27C0}'..='\u{27FF}'
+        | '\u{2900}'..='\u{297F}'
+        | '\u{2B00}'..='\u{2BFF}'
+        | '\u{3030}' | '\u{303D}' | '\u{3297}' | '\u{3299}'
+        | '\u{1F000}'..='\u{1F1E5}'
+        | '\u{1F200}'..='\u{1FAFF}')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names_of_emoji_and_symbols() {
+        assert_eq!(
+            verbalize_symbols("Deploy done ✅ 🚀→prod 👍🏽", SymbolVerbosity::Names),
+            "Deploy done check mark button rocket right arrow prod thumbs up"
+        );
+        assert_eq!(
+            verbalize_symbols("👨‍💻 on call in 🇩🇪, x ≤ 3", SymbolVerbosity::Names),
+            "man technologist on call in flag D E, x less-than or equal to 3"
+        );
+    }
+
+    #[test]
+    fn remove_symbols_and_box_drawing() {
+        assert_eq!(
+            verbalize_symbols("┌──┐ Build ❤️ ok ⚠ © 2024", SymbolVerbosity::Remove),
+            "Build ok © 2024"
+        );
+        assert_eq!(
+            verbalize_symbols("╔═╗ Status ║", SymbolVerbosity::Names),
+            "Status"
+        );
+    }
+
+    #[test]
+    fn keep_and_plain_text_borrow() {
+        for (text, verbosity) in &[
+            ("🚀 launch", SymbolVerbosity::Keep),
+            ("plain text, 3 < 4", SymbolVerbosity::Names),
+        ] {
+            match verbalize_symbols(text, *verbosity) {
+                Cow::Borrowed(borrowed) => assert_eq!(borrowed, *text),
+                Cow::Owned(owned) => panic!("Expected unchanged text, got: {}", owned),
+            }
+        }
+    }
+}
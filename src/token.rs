@@ -1,27 +1,134 @@
-#[derive(Debug, PartialEq, Eq)]
+use crate::phoneme::PhoneticAlphabet;
+use crate::say_as::Interpretation;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::time::Duration;
+
+/// Words that are commonly abbreviated with a trailing dot
+/// that does not end the sentence, lowercase and without
+/// the final dot.
+const ABBREVIATIONS: &[&str] = &[
+    "approx", "bzw", "ca", "cf", "dept", "dr", "etc", "evtl", "fig", "ggf", "inc", "jr", "ltd",
+    "mr", "mrs", "ms", "nr", "prof", "sr", "st", "usw", "vgl", "vol", "vs",
+];
+
+/// A piece of speech, as produced by the [`Tokenizer`](struct.Tokenizer.html)
+/// and the other front ends, and consumed by the backends.
+///
+/// Text may borrow from the source it was parsed from, use
+/// `into_owned` to detach it.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token<'a> {
     /// A piece of un-emphasised speech.
-    Normal(&'a str),
+    Normal(Cow<'a, str>),
     /// A piece of emphasised speech.
-    Emphasised(&'a str),
+    Emphasised(Cow<'a, str>),
+    /// A piece of strongly emphasised speech, written like
+    /// `__critical__`.
+    Strong(Cow<'a, str>),
+    /// A piece of speech with less emphasis than usual, written
+    /// like `{reduced: by the way}`.
+    Reduced(Cow<'a, str>),
     // A pause with specified duration
     Pause(PauseDuration),
+    /// Start of a span spoken with different prosody, until
+    /// the matching `ProsodyEnd`.
+    ProsodyStart(Prosody),
+    /// End of the innermost span with the given prosody.
+    ProsodyEnd(Prosody),
+    /// Text with a hint on how to read it, like `{tel: 555-0199}`.
+    SayAs(Interpretation, Cow<'a, str>),
+    /// Text with its pronunciation, like `{ipa:ˈtɑːvlə|tavla}`.
+    /// Backends that cannot use the phonemes speak the text.
+    Phoneme {
+        alphabet: PhoneticAlphabet,
+        phonemes: Cow<'a, str>,
+        text: Cow<'a, str>,
+    },
+    /// The byte range of the source that the following tokens
+    /// were parsed from, passed on to backends as a marker.
+    Mark(Range<usize>),
 }
 
 impl<'a> Token<'a> {
-    fn new(text: &str, style: Style) -> Token {
+    /// Converts into a token that does not borrow from its source.
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::Normal(text) => Token::Normal(Cow::Owned(text.into_owned())),
+            Token::Emphasised(text) => Token::Emphasised(Cow::Owned(text.into_owned())),
+            Token::Strong(text) => Token::Strong(Cow::Owned(text.into_owned())),
+            Token::Reduced(text) => Token::Reduced(Cow::Owned(text.into_owned())),
+            Token::Pause(pause) => Token::Pause(pause),
+            Token::ProsodyStart(prosody) => Token::ProsodyStart(prosody),
+            Token::ProsodyEnd(prosody) => Token::ProsodyEnd(prosody),
+            Token::Mark(span) => Token::Mark(span),
+            Token::SayAs(interpretation, text) => {
+                Token::SayAs(interpretation, Cow::Owned(text.into_owned()))
+            }
+            Token::Phoneme {
+                alphabet,
+                phonemes,
+                text,
+            } => Token::Phoneme {
+                alphabet,
+                phonemes: Cow::Owned(phonemes.into_owned()),
+                text: Cow::Owned(text.into_owned()),
+            },
+        }
+    }
+
+    /// Changes the text of tokens that are read as written,
+    /// keeping their emphasis. Say-as hints, phonemes and tokens
+    /// without text are returned as they are.
+    pub(crate) fn map_text<F>(self, f: F) -> Token<'a>
+    where
+        F: FnOnce(Cow<'a, str>) -> Cow<'a, str>,
+    {
+        match self {
+            Token::Normal(text) => Token::Normal(f(text)),
+            Token::Emphasised(text) => Token::Emphasised(f(text)),
+            Token::Strong(text) => Token::Strong(f(text)),
+            Token::Reduced(text) => Token::Reduced(f(text)),
+            other => other,
+        }
+    }
+
+    /// Checks if the token has text to speak, rather than only
+    /// pausing, changing prosody or marking a position.
+    pub(crate) fn is_spoken(&self) -> bool {
+        !matches!(
+            self,
+            Token::Pause(_) | Token::ProsodyStart(_) | Token::ProsodyEnd(_) | Token::Mark(_)
+        )
+    }
+
+    fn new(text: Cow<'a, str>, style: Style, reduced: bool) -> Token<'a> {
         match style {
+            Style::Strong => Token::Strong(text),
             Style::Emphasised => Token::Emphasised(text),
+            Style::Unemphasised if reduced => Token::Reduced(text),
             Style::Unemphasised => Token::Normal(text),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// The length of a pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PauseDuration {
+    /// A pause between sentences, written as a single dot.
     Sentence,
+    /// A pause between paragraphs, written as two dots
+    /// or a blank line.
     Paragraph,
-    Seconds(u32),
+    /// A longer pause, written as a run of more than two dots.
+    /// Holds the number of dots beyond the second.
+    DotRun(u32),
+    /// A short pause at the end of a line, when reading lines
+    /// as verse with [`LineMode::Verse`](enum.LineMode.html).
+    Line,
+    /// A pause of exactly the given length, written like
+    /// `{pause 250ms}` or `{pause 1.5s}`.
+    Exact(Duration),
 }
 
 impl PauseDuration {
@@ -33,45 +140,143 @@ impl PauseDuration {
                 0 => None,
                 1 => Some(PauseDuration::Sentence),
                 2 => Some(PauseDuration::Paragraph),
-                n => Some(PauseDuration::Seconds((n - 2) as u32)),
+                n => Some(PauseDuration::DotRun((n - 2) as u32)),
             }
         }
     }
 }
 
-#[derive(Clone, Copy)]
+/// How single line breaks in tavla markup are read. Blank lines
+/// always end paragraphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineMode {
+    /// Every line is spoken as a separate piece of text, but
+    /// without a pause in between. This is the default.
+    #[default]
+    Split,
+    /// Hard-wrapped lines are joined into one text, like in
+    /// man pages or commit messages.
+    Prose,
+    /// Every line ends with a short pause, like in verse.
+    Verse,
+    /// Every line ends a sentence, like the items of a list.
+    List,
+}
+
+/// A change in rate, pitch or volume, applied to a span
+/// like `{slow: the important part}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prosody {
+    Slow,
+    Fast,
+    Low,
+    High,
+    Soft,
+    Loud,
+}
+
+impl Prosody {
+    fn from_keyword(keyword: &str) -> Option<Prosody> {
+        match keyword {
+            "slow" => Some(Prosody::Slow),
+            "fast" => Some(Prosody::Fast),
+            "low" => Some(Prosody::Low),
+            "high" => Some(Prosody::High),
+            "soft" => Some(Prosody::Soft),
+            "loud" => Some(Prosody::Loud),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum Style {
     // Unemphasised spoken text.
     Unemphasised,
-    // Pauses and consecutive newlines.
+    // Text between single underscores.
     Emphasised,
+    // Text between double underscores.
+    Strong,
 }
 
-impl Style {
-    fn flip(&self) -> Style {
-        if let Style::Unemphasised = self {
-            Style::Emphasised
-        } else {
-            Style::Unemphasised
-        }
-    }
+/// A span that is open until its closing brace.
+enum OpenSpan {
+    Prosody(Prosody),
+    Reduced,
 }
 
+/// Splits tavla markup into [`Token`](enum.Token.html)s.
 pub struct Tokenizer<'a> {
+    source: &'a str,
     rest: &'a str,
-    style: Style,
+    /// The byte range of the last token in the source.
+    span: Range<usize>,
+    /// Currently open emphasis styles, innermost last.
+    styles: Vec<Style>,
+    /// Currently open spans, innermost last.
+    spans: Vec<OpenSpan>,
+    line_mode: LineMode,
 }
 
 impl<'a> Tokenizer<'a> {
     pub fn new(source: &'a str) -> Tokenizer<'a> {
         Tokenizer {
+            source,
             rest: source,
-            style: Style::Unemphasised,
+            span: 0..0,
+            styles: Vec::new(),
+            spans: Vec::new(),
+            line_mode: LineMode::default(),
         }
     }
+
+    /// Reads single line breaks according to the given mode,
+    /// instead of splitting text without a pause.
+    pub fn with_line_mode(mut self, line_mode: LineMode) -> Tokenizer<'a> {
+        self.line_mode = line_mode;
+        self
+    }
 }
 
 impl<'a> Tokenizer<'a> {
+    /// Pairs every token with the byte range of the source it
+    /// was parsed from, e.g. to highlight what is spoken.
+    ///
+    /// Ranges of text exclude surrounding whitespace and include
+    /// escapes. Tokens that close spans at the end of the input
+    /// have an empty range at its end.
+    pub fn spanned(self) -> SpannedTokens<'a> {
+        SpannedTokens { tokenizer: self }
+    }
+
+    /// The innermost open emphasis style.
+    fn style(&self) -> Style {
+        self.styles.last().copied().unwrap_or(Style::Unemphasised)
+    }
+
+    /// Applies an emphasis marker, which ends its own style if
+    /// open, resuming the one around it, or starts it otherwise.
+    fn toggle_style(&mut self, marker: Style) {
+        match self.styles.iter().rposition(|style| *style == marker) {
+            Some(idx) => {
+                self.styles.remove(idx);
+            }
+            None => self.styles.push(marker),
+        }
+    }
+
+    /// The byte offset of the rest in the source.
+    fn offset(&self) -> usize {
+        self.source.len() - self.rest.len()
+    }
+
+    /// Records the span of a token from the given offset to the
+    /// current one.
+    fn spanned_from(&mut self, start: usize, token: Token<'a>) -> Option<Token<'a>> {
+        self.span = start..self.offset();
+        Some(token)
+    }
+
     fn consume_token(&mut self) -> Option<Token<'a>> {
         let leading_pause = self.consume_leading_pause();
         leading_pause.or_else(|| self.consume_text())
@@ -83,6 +288,7 @@ impl<'a> Tokenizer<'a> {
     // The index of the first non-whitespace and non-pause character
     /// is returned.
     fn consume_leading_pause(&mut self) -> Option<Token<'a>> {
+        let start = self.offset();
         let mut pause_count = 0;
         let mut newline_count = 0;
 
@@ -105,71 +311,395 @@ impl<'a> Tokenizer<'a> {
             })
             .map(|(idx, _)| idx)
             .next()
-            .unwrap_or_else(|| self.rest.len());
+            .unwrap_or(self.rest.len());
 
         self.rest = &self.rest[first_non_whitespace_idx..];
 
-        PauseDuration::from(pause_count, newline_count).map(Token::Pause)
+        // Line breaks between text may be pauses, depending on the mode
+        let is_line_break = newline_count == 1 && start > 0 && !self.rest.is_empty();
+        let pause = PauseDuration::from(pause_count, newline_count).or(match self.line_mode {
+            LineMode::Verse if is_line_break => Some(PauseDuration::Line),
+            LineMode::List if is_line_break => Some(PauseDuration::Sentence),
+            _ => None,
+        })?;
+        self.spanned_from(start, Token::Pause(pause))
     }
 
-    /// Consumes text until encountering a newline or change in
-    /// emphasis.
+    /// Consumes text until encountering a newline, a pause,
+    /// a change in emphasis or the start or end of a span.
     fn consume_text(&mut self) -> Option<Token<'a>> {
-        let chars = self.rest.char_indices();
-        let state_change = chars
-            .skip_while(|(_, c)| match c {
-                '_' | '.' | '\n' => false,
-                _ => true,
-            })
-            .next();
+        let (text, state_change) = self.scan_text();
+        let start = self.offset();
 
         if let Some((token_end_idx, c)) = state_change {
             if token_end_idx > 0 {
                 // first some text, then a pause or emphasis, consume the text
-                let text = &self.rest[0..token_end_idx].trim_end();
+                let text_len = self.rest[..token_end_idx].trim_end().len();
                 self.rest = &self.rest[token_end_idx..];
-                Some(Token::new(text, self.style))
+                self.span = start..start + text_len;
+                Some(self.text_token(text))
             } else if c == '_' {
                 // first non-whitespace is emphasis start, consume and recur
-                self.style = self.style.flip();
-                self.rest = &self.rest[token_end_idx..];
-                let next_char_idx = self
-                    .rest
-                    .char_indices()
-                    .skip(1) // skip to first char after emphasis
-                    .next()
-                    .map(|(idx, _)| idx)
-                    .unwrap_or_else(|| self.rest.len());
-                self.rest = &self.rest[next_char_idx..];
+                if self.rest.starts_with("__") {
+                    self.toggle_style(Style::Strong);
+                    self.rest = &self.rest["__".len()..];
+                } else {
+                    self.toggle_style(Style::Emphasised);
+                    self.rest = &self.rest[c.len_utf8()..];
+                }
                 self.next()
+            } else if let Some((duration, pause_len)) = explicit_pause(self.rest) {
+                self.rest = &self.rest[pause_len..];
+                self.spanned_from(start, Token::Pause(PauseDuration::Exact(duration)))
+            } else if c == '{' {
+                let (span, span_start_len) =
+                    span_start(self.rest).expect("scan_text only stops at valid span starts");
+                self.rest = &self.rest[span_start_len..];
+                match span {
+                    Span::Prosody(prosody) => {
+                        self.spans.push(OpenSpan::Prosody(prosody));
+                        self.spanned_from(start, Token::ProsodyStart(prosody))
+                    }
+                    Span::Reduced => {
+                        self.spans.push(OpenSpan::Reduced);
+                        self.next()
+                    }
+                    Span::SayAs(interpretation) => {
+                        let (text, literal_len, _) = literal(self.rest);
+                        self.rest = &self.rest[literal_len..];
+                        self.spanned_from(start, Token::SayAs(interpretation, text))
+                    }
+                    Span::Phoneme(alphabet) => {
+                        let (content, literal_len, _) = literal(self.rest);
+                        self.rest = &self.rest[literal_len..];
+                        let (phonemes, text) = split_pronunciation(content);
+                        self.spanned_from(
+                            start,
+                            Token::Phoneme {
+                                alphabet,
+                                phonemes,
+                                text,
+                            },
+                        )
+                    }
+                }
+            } else if c == '}' {
+                let span = self
+                    .spans
+                    .pop()
+                    .expect("scan_text only stops in open spans");
+                self.rest = &self.rest[c.len_utf8()..];
+                match span {
+                    OpenSpan::Prosody(prosody) => {
+                        self.spanned_from(start, Token::ProsodyEnd(prosody))
+                    }
+                    OpenSpan::Reduced => self.next(),
+                }
             } else {
                 // starts with some pause construct, this should not be possible
                 unreachable!("consume_leading_pause should have consumed leading whitespace")
             }
         } else {
             // text includes the rest of the string
-            let remaining_text = &self.rest[0..self.rest.len()];
-            let remaining_text = remaining_text.trim_end();
+            self.span = start..start + self.rest.trim_end().len();
             self.rest = &self.rest[self.rest.len()..];
 
-            if remaining_text.is_empty() {
+            if text.is_empty() {
                 None
             } else {
-                Some(Token::new(remaining_text, self.style))
+                Some(self.text_token(text))
+            }
+        }
+    }
+
+    fn text_token(&self, text: Cow<'a, str>) -> Token<'a> {
+        let reduced = self
+            .spans
+            .iter()
+            .any(|span| matches!(span, OpenSpan::Reduced));
+        Token::new(text, self.style(), reduced)
+    }
+
+    /// Closes the innermost span left open at the end of the input
+    /// that needs a closing token.
+    fn close_open_span(&mut self) -> Option<Token<'a>> {
+        while let Some(span) = self.spans.pop() {
+            if let OpenSpan::Prosody(prosody) = span {
+                let end = self.source.len();
+                return self.spanned_from(end, Token::ProsodyEnd(prosody));
+            }
+        }
+        None
+    }
+
+    /// Finds the end of the text at the start of the rest, which
+    /// is the next newline, pause, emphasis or span boundary that
+    /// has not been escaped with a backslash.
+    ///
+    /// Returns the text with escapes resolved and without trailing
+    /// whitespace, along with the index and character of the state
+    /// change, if any.
+    fn scan_text(&self) -> (Cow<'a, str>, Option<(usize, char)>) {
+        let rest = self.rest;
+        let mut unescaped: Option<String> = None;
+        let mut unescaped_until = 0;
+        let mut state_change = None;
+        let mut prev = None;
+        let mut chars = rest.char_indices().peekable();
+
+        while let Some((idx, c)) = chars.next() {
+            let next = chars.peek().map(|&(_, c)| c);
+            let ends_text = match c {
+                '\\' if next.is_some_and(is_escapable) => {
+                    let (escaped_idx, escaped) = chars.next().expect("peeked escaped char");
+                    let unescaped = unescaped.get_or_insert_with(String::new);
+                    unescaped.push_str(&rest[unescaped_until..idx]);
+                    unescaped.push(escaped);
+                    unescaped_until = escaped_idx + escaped.len_utf8();
+                    prev = Some(escaped);
+                    continue;
+                }
+                '\n' if self.line_mode == LineMode::Prose && !is_blank_line(&rest[idx + 1..]) => {
+                    // Joins hard-wrapped lines with a single space
+                    let next_line = &rest[idx + 1..];
+                    let indent = next_line.len() - next_line.trim_start().len();
+                    let unescaped = unescaped.get_or_insert_with(String::new);
+                    unescaped.push_str(rest[unescaped_until..idx].trim_end());
+                    unescaped.push(' ');
+                    unescaped_until = idx + 1 + indent;
+                    while chars.next_if(|&(idx, _)| idx < unescaped_until).is_some() {}
+                    prev = Some(' ');
+                    continue;
+                }
+                '\n' => true,
+                '_' => is_emphasis_marker(prev, next),
+                '.' => is_pause_dot(&rest[..idx], prev, next),
+                '{' => span_start(&rest[idx..]).is_some() || explicit_pause(&rest[idx..]).is_some(),
+                '}' => !self.spans.is_empty(),
+                _ => false,
+            };
+
+            if ends_text {
+                state_change = Some((idx, c));
+                break;
+            }
+            prev = Some(c);
+        }
+
+        let text_end_idx = state_change.map_or(rest.len(), |(idx, _)| idx);
+        let text = match unescaped {
+            Some(mut unescaped) => {
+                unescaped.push_str(&rest[unescaped_until..text_end_idx]);
+                unescaped.truncate(unescaped.trim_end().len());
+                Cow::Owned(unescaped)
             }
+            None => Cow::Borrowed(rest[..text_end_idx].trim_end()),
+        };
+
+        (text, state_change)
+    }
+}
+
+/// Tokens of a [`Tokenizer`](struct.Tokenizer.html) along
+/// with the byte range of the source they were parsed from.
+pub struct SpannedTokens<'a> {
+    tokenizer: Tokenizer<'a>,
+}
+
+impl<'a> Iterator for SpannedTokens<'a> {
+    type Item = (Token<'a>, Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.tokenizer.next()?;
+        Some((token, self.tokenizer.span.clone()))
+    }
+}
+
+/// Checks if the line at the start of the text is empty or
+/// only whitespace, which is also the case at the end.
+fn is_blank_line(text: &str) -> bool {
+    let line_end = text.find('\n').unwrap_or(text.len());
+    text[..line_end].trim().is_empty()
+}
+
+/// Characters that lose their special meaning when preceded
+/// by a backslash.
+pub(crate) fn is_escapable(c: char) -> bool {
+    matches!(c, '.' | '_' | '{' | '}' | '\\')
+}
+
+/// The kind of span introduced by a keyword like `{slow:`.
+pub(crate) enum Span {
+    /// Marked up content spoken with different prosody.
+    Prosody(Prosody),
+    /// Marked up content spoken with reduced emphasis.
+    Reduced,
+    /// Literal text with a hint on how to read it.
+    SayAs(Interpretation),
+    /// Literal phonemes, followed by the written text.
+    Phoneme(PhoneticAlphabet),
+}
+
+/// Parses a span start like `{slow:` at the start of the given
+/// text, returning its kind and length in bytes.
+pub(crate) fn span_start(text: &str) -> Option<(Span, usize)> {
+    let after_brace = text.strip_prefix('{')?;
+    let colon_idx = after_brace.find(':')?;
+    let keyword = &after_brace[..colon_idx];
+    let span = Prosody::from_keyword(keyword)
+        .map(Span::Prosody)
+        .or_else(|| (keyword == "reduced").then_some(Span::Reduced))
+        .or_else(|| Interpretation::from_keyword(keyword).map(Span::SayAs))
+        .or_else(|| PhoneticAlphabet::from_keyword(keyword).map(Span::Phoneme))?;
+    Some((span, '{'.len_utf8() + colon_idx + ':'.len_utf8()))
+}
+
+/// Parses an explicit pause like `{pause 250ms}` at the start
+/// of the given text, returning its duration and length in bytes.
+pub(crate) fn explicit_pause(text: &str) -> Option<(Duration, usize)> {
+    let after_keyword = text.strip_prefix("{pause")?;
+    if !after_keyword.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let close_idx = after_keyword.find('}')?;
+    let duration = parse_duration(&after_keyword[..close_idx])?;
+    Some((duration, "{pause".len() + close_idx + '}'.len_utf8()))
+}
+
+/// Parses time designations like `250ms` or `1.5s`, as used
+/// in explicit pauses and SSML.
+pub(crate) fn parse_duration(time: &str) -> Option<Duration> {
+    let time = time.trim();
+    let (number, factor) = if let Some(millis) = time.strip_suffix("ms") {
+        (millis, 1.0)
+    } else {
+        (time.strip_suffix('s')?, 1000.0)
+    };
+
+    let number: f64 = number.trim_end().parse().ok()?;
+    if number.is_finite() && number >= 0.0 && number * factor <= u32::MAX as f64 {
+        Some(Duration::from_millis((number * factor).round() as u64))
+    } else {
+        None
+    }
+}
+
+/// Reads the content of a say-as span up to the closing brace,
+/// without interpreting any markup but escapes.
+///
+/// Returns the trimmed content, the length in bytes that was
+/// consumed, including the closing brace, and whether there was
+/// a closing brace at all.
+pub(crate) fn literal(text: &str) -> (Cow<'_, str>, usize, bool) {
+    let mut unescaped: Option<String> = None;
+    let mut unescaped_until = 0;
+    let mut end = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' if chars.peek().is_some_and(|&(_, next)| is_escapable(next)) => {
+                let (escaped_idx, escaped) = chars.next().expect("peeked escaped char");
+                let unescaped = unescaped.get_or_insert_with(String::new);
+                unescaped.push_str(&text[unescaped_until..idx]);
+                unescaped.push(escaped);
+                unescaped_until = escaped_idx + escaped.len_utf8();
+            }
+            '}' => {
+                end = Some(idx);
+                break;
+            }
+            _ => (),
         }
     }
+
+    let content_end = end.unwrap_or(text.len());
+    let consumed = end.map_or(text.len(), |idx| idx + '}'.len_utf8());
+    let content = match unescaped {
+        Some(mut unescaped) => {
+            unescaped.push_str(&text[unescaped_until..content_end]);
+            Cow::Owned(unescaped.trim().to_string())
+        }
+        None => Cow::Borrowed(text[..content_end].trim()),
+    };
+
+    (content, consumed, end.is_some())
+}
+
+/// Splits the content of a span like `{ipa:ˈtɑːvlə|tavla}` into
+/// phonemes and written text. Without a `|`, the phonemes double
+/// as the text.
+fn split_pronunciation(content: Cow<'_, str>) -> (Cow<'_, str>, Cow<'_, str>) {
+    fn split(content: &str) -> Option<(&str, &str)> {
+        let idx = content.find('|')?;
+        Some((content[..idx].trim_end(), content[idx + 1..].trim_start()))
+    }
+
+    match content {
+        Cow::Borrowed(content) => match split(content) {
+            Some((phonemes, text)) => (Cow::Borrowed(phonemes), Cow::Borrowed(text)),
+            None => (Cow::Borrowed(content), Cow::Borrowed(content)),
+        },
+        Cow::Owned(content) => match split(&content) {
+            Some((phonemes, text)) => (Cow::Owned(phonemes.into()), Cow::Owned(text.into())),
+            None => (Cow::Owned(content.clone()), Cow::Owned(content)),
+        },
+    }
+}
+
+/// Underscores inside of words, like in `snake_case_name`,
+/// are spoken as-is, other underscores toggle emphasis.
+pub(crate) fn is_emphasis_marker(prev: Option<char>, next: Option<char>) -> bool {
+    let is_word_char = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    !(is_word_char(prev) && is_word_char(next))
+}
+
+/// Checks if a dot is a pause, rather than a part of a number,
+/// a URL, a file name or a known abbreviation.
+pub(crate) fn is_pause_dot(before: &str, prev: Option<char>, next: Option<char>) -> bool {
+    if prev.is_none_or(char::is_whitespace) {
+        return true;
+    }
+
+    match next {
+        Some('.') => true,
+        // Inside of a word like 3.14, example.com or e.g
+        Some(next) if next.is_alphanumeric() => false,
+        _ => !is_abbreviation(last_word(before)),
+    }
+}
+
+pub(crate) fn last_word(text: &str) -> &str {
+    text.rsplit(char::is_whitespace)
+        .next()
+        .unwrap_or(text)
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+}
+
+/// Checks for known abbreviations and initialisms like `e.g`
+/// or `U.S`, the trailing dot excluded.
+pub(crate) fn is_abbreviation(word: &str) -> bool {
+    let word = word.to_lowercase();
+    let is_initialism = word.contains('.')
+        && word
+            .split('.')
+            .all(|part| part.chars().count() == 1 && part.chars().all(char::is_alphabetic));
+
+    is_initialism || ABBREVIATIONS.contains(&word.as_str())
 }
 
 impl<'a> Iterator for Tokenizer<'a> {
     type Item = Token<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.rest.is_empty() {
+        let token = if self.rest.is_empty() {
             None
         } else {
             self.consume_token()
-        }
+        };
+
+        // Close spans left open at the end of the input
+        token.or_else(|| self.close_open_span())
     }
 }
 
@@ -182,7 +712,7 @@ mod test {
         let mut tokenizer = Tokenizer::new("holodrio");
         assert_eq!(
             tokenizer.next().expect("Expected a single token"),
-            Token::Normal("holodrio")
+            Token::Normal("holodrio".into())
         );
         assert_eq!(tokenizer.next(), None);
         assert!(tokenizer.next().is_none());
@@ -192,8 +722,8 @@ mod test {
     #[test]
     fn emphasised_string() {
         let mut tokenizer = Tokenizer::new("   holodrio _there_");
-        assert_eq!(tokenizer.next(), Some(Token::Normal("holodrio")));
-        assert_eq!(tokenizer.next(), Some(Token::Emphasised("there")));
+        assert_eq!(tokenizer.next(), Some(Token::Normal("holodrio".into())));
+        assert_eq!(tokenizer.next(), Some(Token::Emphasised("there".into())));
         assert_eq!(tokenizer.next(), None);
         assert!(tokenizer.next().is_none());
         assert!(tokenizer.next().is_none());
@@ -202,8 +732,8 @@ mod test {
     #[test]
     fn emphasised_unclosed_string() {
         let mut tokenizer = Tokenizer::new("holodrio _there");
-        assert_eq!(tokenizer.next(), Some(Token::Normal("holodrio")));
-        assert_eq!(tokenizer.next(), Some(Token::Emphasised("there")));
+        assert_eq!(tokenizer.next(), Some(Token::Normal("holodrio".into())));
+        assert_eq!(tokenizer.next(), Some(Token::Emphasised("there".into())));
         assert_eq!(tokenizer.next(), None);
         assert!(tokenizer.next().is_none());
         assert!(tokenizer.next().is_none());
@@ -212,8 +742,8 @@ mod test {
     #[test]
     fn emphasised_unclosed_string_with_pause_after() {
         let mut tokenizer = Tokenizer::new("holodrio _there.");
-        assert_eq!(tokenizer.next(), Some(Token::Normal("holodrio")));
-        assert_eq!(tokenizer.next(), Some(Token::Emphasised("there")));
+        assert_eq!(tokenizer.next(), Some(Token::Normal("holodrio".into())));
+        assert_eq!(tokenizer.next(), Some(Token::Emphasised("there".into())));
         assert_eq!(
             tokenizer.next(),
             Some(Token::Pause(PauseDuration::Sentence))
@@ -226,17 +756,17 @@ mod test {
     #[test]
     fn mixed_string() {
         let mut tokenizer = Tokenizer::new("plain ..\n\n\n\n. _emph_. plain again ..");
-        assert_eq!(tokenizer.next(), Some(Token::Normal("plain")));
+        assert_eq!(tokenizer.next(), Some(Token::Normal("plain".into())));
         assert_eq!(
             tokenizer.next(),
-            Some(Token::Pause(PauseDuration::Seconds(1)))
+            Some(Token::Pause(PauseDuration::DotRun(1)))
         );
-        assert_eq!(tokenizer.next(), Some(Token::Emphasised("emph")));
+        assert_eq!(tokenizer.next(), Some(Token::Emphasised("emph".into())));
         assert_eq!(
             tokenizer.next(),
             Some(Token::Pause(PauseDuration::Sentence))
         );
-        assert_eq!(tokenizer.next(), Some(Token::Normal("plain again")));
+        assert_eq!(tokenizer.next(), Some(Token::Normal("plain again".into())));
         assert_eq!(
             tokenizer.next(),
             Some(Token::Pause(PauseDuration::Paragraph))
@@ -267,23 +797,326 @@ mod test {
         assert_eq!(
             tokens,
             vec![
-                Token::Normal("Welcome, you have reached the suicide cell service"),
-                Token::Normal("hotline, how may we help you?"),
-                Token::Normal("Press"),
-                Token::Emphasised("one"),
-                Token::Normal("if your machine does not work for you"),
-                Token::Normal("as intended and you require technical support"),
+                Token::Normal("Welcome, you have reached the suicide cell service".into()),
+                Token::Normal("hotline, how may we help you?".into()),
+                Token::Normal("Press".into()),
+                Token::Emphasised("one".into()),
+                Token::Normal("if your machine does not work for you".into()),
+                Token::Normal("as intended and you require technical support".into()),
                 Token::Pause(PauseDuration::Paragraph),
-                Token::Normal("Press"),
-                Token::Emphasised("two"),
-                Token::Normal("if you changed your mind and want your"),
-                Token::Normal("suicide fee refunded"),
+                Token::Normal("Press".into()),
+                Token::Emphasised("two".into()),
+                Token::Normal("if you changed your mind and want your".into()),
+                Token::Normal("suicide fee refunded".into()),
+                Token::Pause(PauseDuration::Paragraph),
+                Token::Normal("Press".into()),
+                Token::Emphasised("three".into()),
+                Token::Normal("to learn more about the history of".into()),
+                Token::Normal("McKillys Suicide Cells".into()),
+                Token::Pause(PauseDuration::DotRun(1))
+            ]
+        );
+    }
+
+    #[test]
+    fn line_modes() {
+        const WRAPPED: &str = "
+            Roses are red, _violets
+            are blue_.
+            Sugar is sweet
+
+            And so are you";
+        let tokens =
+            |mode| -> Vec<Token> { Tokenizer::new(WRAPPED).with_line_mode(mode).collect() };
+
+        assert_eq!(
+            tokens(LineMode::Prose),
+            vec![
+                Token::Normal("Roses are red,".into()),
+                Token::Emphasised("violets are blue".into()),
+                Token::Pause(PauseDuration::Sentence),
+                Token::Normal("Sugar is sweet".into()),
                 Token::Pause(PauseDuration::Paragraph),
-                Token::Normal("Press"),
-                Token::Emphasised("three"),
-                Token::Normal("to learn more about the history of"),
-                Token::Normal("McKillys Suicide Cells"),
-                Token::Pause(PauseDuration::Seconds(1))
+                Token::Normal("And so are you".into()),
+            ]
+        );
+        assert_eq!(
+            tokens(LineMode::Verse),
+            vec![
+                Token::Normal("Roses are red,".into()),
+                Token::Emphasised("violets".into()),
+                Token::Pause(PauseDuration::Line),
+                Token::Emphasised("are blue".into()),
+                Token::Pause(PauseDuration::Sentence),
+                Token::Normal("Sugar is sweet".into()),
+                Token::Pause(PauseDuration::Paragraph),
+                Token::Normal("And so are you".into()),
+            ]
+        );
+        assert_eq!(
+            tokens(LineMode::List)[2],
+            Token::Pause(PauseDuration::Sentence)
+        );
+        assert_eq!(
+            Tokenizer::new(WRAPPED).collect::<Vec<_>>(),
+            tokens(LineMode::Split)
+        );
+    }
+
+    #[test]
+    fn escaped_pause_and_emphasis() {
+        let tokens: Vec<Token> =
+            Tokenizer::new(r"type \_\_init\_\_ then hit enter\... _now_\\").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Normal("type __init__ then hit enter.".into()),
+                Token::Pause(PauseDuration::Paragraph),
+                Token::Emphasised("now".into()),
+                Token::Normal("\\".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn dots_inside_of_words() {
+        let tokens: Vec<Token> =
+            Tokenizer::new("Upgrade to version 3.14 from example.com, e.g. with main.rs. Done")
+                .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Normal("Upgrade to version 3.14 from example.com, e.g. with main.rs".into()),
+                Token::Pause(PauseDuration::Sentence),
+                Token::Normal("Done".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn known_abbreviations() {
+        let tokens: Vec<Token> = Tokenizer::new("Ask Dr. Who, z.B. at 5 p.m. or so etc.").collect();
+        assert_eq!(
+            tokens,
+            vec![Token::Normal(
+                "Ask Dr. Who, z.B. at 5 p.m. or so etc.".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn abbreviation_before_dot_run() {
+        let tokens: Vec<Token> = Tokenizer::new("and so on etc... fine").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Normal("and so on etc".into()),
+                Token::Pause(PauseDuration::DotRun(1)),
+                Token::Normal("fine".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn underscores_inside_of_words() {
+        let tokens: Vec<Token> = Tokenizer::new("rename snake_case_name to _camel_case_").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Normal("rename snake_case_name to".into()),
+                Token::Emphasised("camel_case".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn prosody_spans() {
+        let tokens: Vec<Token> =
+            Tokenizer::new("Build failed on {slow: {high: _main_ branch}}. {fast: Details follow}")
+                .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Normal("Build failed on".into()),
+                Token::ProsodyStart(Prosody::Slow),
+                Token::ProsodyStart(Prosody::High),
+                Token::Emphasised("main".into()),
+                Token::Normal("branch".into()),
+                Token::ProsodyEnd(Prosody::High),
+                Token::ProsodyEnd(Prosody::Slow),
+                Token::Pause(PauseDuration::Sentence),
+                Token::ProsodyStart(Prosody::Fast),
+                Token::Normal("Details follow".into()),
+                Token::ProsodyEnd(Prosody::Fast),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_prosody_span() {
+        let tokens: Vec<Token> = Tokenizer::new("{loud: {soft: hey   ").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::ProsodyStart(Prosody::Loud),
+                Token::ProsodyStart(Prosody::Soft),
+                Token::Normal("hey".into()),
+                Token::ProsodyEnd(Prosody::Soft),
+                Token::ProsodyEnd(Prosody::Loud),
+            ]
+        );
+    }
+
+    #[test]
+    fn say_as_spans() {
+        let tokens: Vec<Token> =
+            Tokenizer::new("Ticket {chars: INC-4711.b} at {time: 14:05}. Call {tel: 555 \\} 1}")
+                .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Normal("Ticket".into()),
+                Token::SayAs(Interpretation::Characters, "INC-4711.b".into()),
+                Token::Normal("at".into()),
+                Token::SayAs(Interpretation::Time, "14:05".into()),
+                Token::Pause(PauseDuration::Sentence),
+                Token::Normal("Call".into()),
+                Token::SayAs(Interpretation::Telephone, "555 } 1".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn say_as_inside_of_prosody() {
+        let tokens: Vec<Token> = Tokenizer::new("{slow: host {nato: db1} is down").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::ProsodyStart(Prosody::Slow),
+                Token::Normal("host".into()),
+                Token::SayAs(Interpretation::Nato, "db1".into()),
+                Token::Normal("is down".into()),
+                Token::ProsodyEnd(Prosody::Slow),
+            ]
+        );
+    }
+
+    #[test]
+    fn phoneme_spans() {
+        let tokens: Vec<Token> =
+            Tokenizer::new("Welcome to {ipa:ˈtɑːvlə | tavla}, by {sapi: n ih1 k}").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Normal("Welcome to".into()),
+                Token::Phoneme {
+                    alphabet: PhoneticAlphabet::Ipa,
+                    phonemes: "ˈtɑːvlə".into(),
+                    text: "tavla".into(),
+                },
+                Token::Normal(", by".into()),
+                Token::Phoneme {
+                    alphabet: PhoneticAlphabet::Sapi,
+                    phonemes: "n ih1 k".into(),
+                    text: "n ih1 k".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn emphasis_levels() {
+        let tokens: Vec<Token> =
+            Tokenizer::new("_important_ and __critical__ {reduced: by the _way_} __done").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Emphasised("important".into()),
+                Token::Normal("and".into()),
+                Token::Strong("critical".into()),
+                Token::Reduced("by the".into()),
+                Token::Emphasised("way".into()),
+                Token::Strong("done".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn map_text_keeps_style() {
+        let upper = |token: Token<'static>| token.map_text(|text| text.to_uppercase().into());
+        assert_eq!(
+            upper(Token::Strong("now".into())),
+            Token::Strong("NOW".into())
+        );
+        assert_eq!(
+            upper(Token::Reduced("btw".into())),
+            Token::Reduced("BTW".into())
+        );
+        let say_as = Token::SayAs(Interpretation::Characters, "db1".into());
+        assert_eq!(upper(say_as.clone()), say_as);
+    }
+
+    #[test]
+    fn nested_emphasis() {
+        let tokens: Vec<Token> = Tokenizer::new("_one __two__ three_ four").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Emphasised("one".into()),
+                Token::Strong("two".into()),
+                Token::Emphasised("three".into()),
+                Token::Normal("four".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn explicit_pauses() {
+        let tokens: Vec<Token> =
+            Tokenizer::new("Wait{pause 250ms}for it {pause 1.5 s} {pause} {pause 3 minutes}")
+                .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Normal("Wait".into()),
+                Token::Pause(PauseDuration::Exact(Duration::from_millis(250))),
+                Token::Normal("for it".into()),
+                Token::Pause(PauseDuration::Exact(Duration::from_millis(1500))),
+                Token::Normal("{pause} {pause 3 minutes}".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn braces_without_span() {
+        let tokens: Vec<Token> = Tokenizer::new("fn main() {} {json: 1} \\{slow: no\\}").collect();
+        assert_eq!(
+            tokens,
+            vec![Token::Normal("fn main() {} {json: 1} {slow: no}".into())]
+        );
+    }
+
+    #[test]
+    fn source_spans() {
+        let source = "Hi  _you_.\n\n{slow: {chars: db1} \\_x}";
+        let spans: Vec<_> = Tokenizer::new(source)
+            .spanned()
+            .map(|(token, span)| (token, &source[span]))
+            .collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                (Token::Normal("Hi".into()), "Hi"),
+                (Token::Emphasised("you".into()), "you"),
+                (Token::Pause(PauseDuration::Paragraph), ".\n\n"),
+                (Token::ProsodyStart(Prosody::Slow), "{slow:"),
+                (
+                    Token::SayAs(Interpretation::Characters, "db1".into()),
+                    "{chars: db1}"
+                ),
+                (Token::Normal("_x".into()), "\\_x"),
+                (Token::ProsodyEnd(Prosody::Slow), "}"),
             ]
         );
     }
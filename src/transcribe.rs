@@ -0,0 +1,107 @@
+//! Transcription of text into phonemes, without speaking it,
+//! e.g. to check how lexicon entries are pronounced.
+
+use crate::phoneme::PhoneticAlphabet;
+use crate::token::Token;
+use crate::utterance::Utterance;
+use failure::Fail;
+use std::borrow::Cow;
+use std::fmt::{Debug, Display};
+
+/// A trait for voices that can tell how they would pronounce
+/// text, as phonemes of a [`PhoneticAlphabet`](enum.PhoneticAlphabet.html).
+///
+/// Transcriptions use the settings of the voice, so that lexicons
+/// and normalization are applied like when speaking.
+pub trait Transcriber {
+    type Error: Fail + Send + Sync + Debug + Display;
+
+    /// Transcribes the whole utterance, with the phonemes of the
+    /// words separated by spaces.
+    fn transcribe_utterance(
+        &self,
+        utterance: &Utterance,
+        alphabet: PhoneticAlphabet,
+    ) -> Result<String, Self::Error>;
+
+    /// Transcribes every word of the utterance on its own.
+    fn transcribe_words(
+        &self,
+        utterance: &Utterance,
+        alphabet: PhoneticAlphabet,
+    ) -> Result<Vec<Transcription>, Self::Error>;
+
+    /// Transcribes the given tavla markup.
+    fn transcribe<S>(&self, markup: S, alphabet: PhoneticAlphabet) -> Result<String, Self::Error>
+    where
+        S: AsRef<str>,
+    {
+        self.transcribe_utterance(&Utterance::parse(markup.as_ref()), alphabet)
+    }
+}
+
+/// A word and its pronunciation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transcription {
+    pub word: String,
+    pub phonemes: String,
+}
+
+/// Splits the text in the tokens into words, each with a token
+/// to transcribe it with. Spans with a pronunciation or a hint on
+/// how to read them are kept whole, and punctuation around words
+/// is dropped.
+pub(crate) fn words(tokens: &[Token]) -> Vec<(String, Token<'static>)> {
+    let mut words = Vec::new();
+    for token in tokens {
+        match token {
+            Token::Normal(text)
+            | Token::Emphasised(text)
+            | Token::Strong(text)
+            | Token::Reduced(text) => {
+                // Keeps the emphasis, without copying the text each time
+                let style = token.clone().map_text(|_| Cow::Borrowed(""));
+                for word in text.split_whitespace() {
+                    let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+                    if !word.is_empty() {
+                        let word_token = style.clone().map_text(|_| Cow::Owned(word.into()));
+                        words.push((word.to_string(), word_token.into_owned()));
+                    }
+                }
+            }
+            Token::SayAs(_, text) | Token::Phoneme { text, .. } => {
+                words.push((text.to_string(), token.clone().into_owned()))
+            }
+            _ => (),
+        }
+    }
+    words
+}
+
+/// Joins the phonemes that a backend printed for the clauses of
+/// the text, one per line, into a single line.
+pub(crate) fn join_clauses(output: &str) -> String {
+    output.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_into_words() {
+        let utterance =
+            Utterance::parse("Hello, _world_! Call {ipa:ˈtɑːvlə|tavla}. {chars: INC 4}");
+        let split = words(utterance.tokens());
+        let names: Vec<_> = split.iter().map(|(word, _)| word.as_str()).collect();
+        assert_eq!(names, vec!["Hello", "world", "Call", "tavla", "INC 4"]);
+        assert_eq!(split[0].1, Token::Normal("Hello".into()));
+        assert!(matches!(split[3].1, Token::Phoneme { .. }));
+    }
+
+    #[test]
+    fn join_clause_lines() {
+        assert_eq!(join_clauses(" h@l'oU\n w'3:ld\n\n"), "h@l'oU w'3:ld");
+        assert_eq!(join_clauses(""), "");
+    }
+}
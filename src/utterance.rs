@@ -0,0 +1,272 @@
+use crate::markdown;
+use crate::normalize::{self, Language};
+use crate::segment::{self, Segment};
+use crate::ssml::{self, Error as SsmlError};
+use crate::symbols::{self, SymbolVerbosity};
+use crate::token::{PauseDuration, Prosody, Token, Tokenizer};
+use std::borrow::Cow;
+use std::iter::FromIterator;
+use std::time::Duration;
+use std::vec;
+
+/// A piece of speech as a sequence of owned tokens, ready
+/// to be spoken by any [`Voice`](trait.Voice.html).
+///
+/// Utterances can be parsed from markup or built up step
+/// by step, and inspected or transformed before speaking:
+///
+/// ```
+/// use std::time::Duration;
+/// use tavla::{Token, Utterance};
+///
+/// let utterance = Utterance::new()
+///     .text("Disk usage on")
+///     .emphasis("db1")
+///     .text("is at 93 percent")
+///     .pause(Duration::from_millis(500))
+///     .text("Please clean up.")
+///     .map(|token| match token {
+///         Token::Normal(text) => Token::Normal(text.replace("db1", "database one").into()),
+///         Token::Emphasised(text) => Token::Emphasised(text.replace("db1", "database one").into()),
+///         other => other,
+///     });
+///
+/// assert_eq!(utterance.tokens()[1], Token::Emphasised("database one".into()));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Utterance {
+    tokens: Vec<Token<'static>>,
+}
+
+impl Utterance {
+    /// Creates an empty utterance.
+    pub fn new() -> Utterance {
+        Utterance::default()
+    }
+
+    /// Parses tavla markup, where underscores emphasise and
+    /// dots and blank lines pause.
+    pub fn parse(markup: &str) -> Utterance {
+        Tokenizer::new(markup).collect()
+    }
+
+    /// Parses tavla markup like `parse`, inserting a
+    /// [`Token::Mark`](enum.Token.html#variant.Mark) with the
+    /// source range before every piece of spoken text.
+    ///
+    /// Backends pass the marks on, so that what is spoken can be
+    /// traced back to the markup.
+    pub fn parse_with_marks(markup: &str) -> Utterance {
+        let mut tokens = Vec::new();
+        for (token, span) in Tokenizer::new(markup).spanned() {
+            if token.is_spoken() {
+                tokens.push(Token::Mark(span));
+            }
+            tokens.push(token);
+        }
+        tokens.into_iter().collect()
+    }
+
+    /// Parses and validates an SSML document.
+    pub fn from_ssml(ssml: &str) -> Result<Utterance, SsmlError> {
+        ssml::parse(ssml).map(Utterance::from)
+    }
+
+    /// Parses a Markdown document.
+    pub fn from_markdown(markdown: &str) -> Utterance {
+        markdown::parse(markdown).into()
+    }
+
+    /// Appends text that is spoken as is, without interpreting
+    /// any markup.
+    pub fn text<S: Into<String>>(self, text: S) -> Utterance {
+        self.token(Token::Normal(Cow::Owned(text.into())))
+    }
+
+    /// Appends emphasised text.
+    pub fn emphasis<S: Into<String>>(self, text: S) -> Utterance {
+        self.token(Token::Emphasised(Cow::Owned(text.into())))
+    }
+
+    /// Appends strongly emphasised text.
+    pub fn strong<S: Into<String>>(self, text: S) -> Utterance {
+        self.token(Token::Strong(Cow::Owned(text.into())))
+    }
+
+    /// Appends text with less emphasis than usual.
+    pub fn reduced<S: Into<String>>(self, text: S) -> Utterance {
+        self.token(Token::Reduced(Cow::Owned(text.into())))
+    }
+
+    /// Appends a pause of the given duration.
+    pub fn pause(self, duration: Duration) -> Utterance {
+        self.token(Token::Pause(PauseDuration::Exact(duration)))
+    }
+
+    /// Appends the utterance built by `content`, spoken with the
+    /// given prosody.
+    pub fn prosody<F>(self, prosody: Prosody, content: F) -> Utterance
+    where
+        F: FnOnce(Utterance) -> Utterance,
+    {
+        self.token(Token::ProsodyStart(prosody))
+            .extend_with(content(Utterance::new()))
+            .token(Token::ProsodyEnd(prosody))
+    }
+
+    /// Appends the given tavla markup.
+    pub fn markup(self, markup: &str) -> Utterance {
+        self.extend_with(Utterance::parse(markup))
+    }
+
+    /// Appends a single token.
+    pub fn token(mut self, token: Token<'_>) -> Utterance {
+        self.tokens.push(token.into_owned());
+        self
+    }
+
+    fn extend_with(mut self, other: Utterance) -> Utterance {
+        self.tokens.extend(other.tokens);
+        self
+    }
+
+    /// Replaces every token with the result of the given function.
+    pub fn map<F>(self, f: F) -> Utterance
+    where
+        F: FnMut(Token<'static>) -> Token<'static>,
+    {
+        self.tokens.into_iter().map(f).collect()
+    }
+
+    /// Expands numbers, currencies, units, dates, times and
+    /// abbreviations in the text into words of the language.
+    pub fn normalize(self, language: Language) -> Utterance {
+        self.map(|token| normalize::normalize_token(token, language))
+    }
+
+    /// Replaces emoji and symbols in the text with their names,
+    /// or removes them, depending on the verbosity. Text that
+    /// consisted only of symbols is dropped when removed.
+    pub fn verbalize_symbols(self, verbosity: SymbolVerbosity) -> Utterance {
+        self.into_iter()
+            .map(|token| symbols::verbalize_token(token, verbosity))
+            .filter(|token| match token {
+                Token::Normal(text)
+                | Token::Emphasised(text)
+                | Token::Strong(text)
+                | Token::Reduced(text) => !text.is_empty(),
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Splits the utterance into sentences and paragraphs that
+    /// can be spoken one after another.
+    pub fn segments(&self) -> Vec<Segment> {
+        segment::segments(&self.tokens)
+    }
+
+    /// The tokens making up this utterance.
+    pub fn tokens(&self) -> &[Token<'static>] {
+        &self.tokens
+    }
+
+    /// The tokens making up this utterance, for modification.
+    pub fn tokens_mut(&mut self) -> &mut Vec<Token<'static>> {
+        &mut self.tokens
+    }
+
+    pub fn into_tokens(self) -> Vec<Token<'static>> {
+        self.tokens
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+}
+
+impl<'a> From<&'a str> for Utterance {
+    /// Parses the text as tavla markup.
+    fn from(markup: &'a str) -> Self {
+        Utterance::parse(markup)
+    }
+}
+
+impl From<String> for Utterance {
+    /// Parses the text as tavla markup.
+    fn from(markup: String) -> Self {
+        Utterance::parse(&markup)
+    }
+}
+
+impl From<Vec<Token<'static>>> for Utterance {
+    fn from(tokens: Vec<Token<'static>>) -> Self {
+        Utterance { tokens }
+    }
+}
+
+impl<'a> FromIterator<Token<'a>> for Utterance {
+    fn from_iter<I: IntoIterator<Item = Token<'a>>>(iter: I) -> Self {
+        Utterance {
+            tokens: iter.into_iter().map(Token::into_owned).collect(),
+        }
+    }
+}
+
+impl<'a> Extend<Token<'a>> for Utterance {
+    fn extend<I: IntoIterator<Item = Token<'a>>>(&mut self, iter: I) {
+        self.tokens.extend(iter.into_iter().map(Token::into_owned))
+    }
+}
+
+impl IntoIterator for Utterance {
+    type Item = Token<'static>;
+    type IntoIter = vec::IntoIter<Token<'static>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tokens.into_iter()
+    }
+}
+
+impl<'u> IntoIterator for &'u Utterance {
+    type Item = &'u Token<'static>;
+    type IntoIter = std::slice::Iter<'u, Token<'static>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tokens.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_equals_parse() {
+        let built = Utterance::new()
+            .text("Press")
+            .emphasis("one")
+            .token(Token::Pause(PauseDuration::Sentence))
+            .prosody(Prosody::Slow, |u| u.text("or hang up"));
+
+        assert_eq!(built, Utterance::parse("Press _one_. {slow: or hang up}"));
+    }
+
+    #[test]
+    fn builder_text_is_not_markup() {
+        let utterance = Utterance::new().text("_not emphasised_. {slow: no}");
+        assert_eq!(
+            utterance.tokens(),
+            &[Token::Normal("_not emphasised_. {slow: no}".into())]
+        );
+    }
+
+    #[test]
+    fn outlives_source() {
+        let utterance = {
+            let source = String::from("temporary _markup_");
+            Utterance::from(source.as_str())
+        };
+        assert_eq!(utterance.into_tokens().len(), 2);
+    }
+}
@@ -0,0 +1,486 @@
+//! Checks for tavla markup that the [`Tokenizer`](struct.Tokenizer.html)
+//! accepts, but that is likely not what the author meant, like
+//! emphasis that is never closed.
+
+use crate::token::{
+    explicit_pause, is_emphasis_marker, is_escapable, is_pause_dot, literal, span_start, Span,
+};
+use std::fmt::{self, Display, Write};
+use std::ops::Range;
+
+/// How bad a [`Diagnostic`](struct.Diagnostic.html) is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Markup that is spoken differently than it was written.
+    Error,
+    /// Markup that is spoken as written, but looks unintended.
+    Warning,
+}
+
+/// A kind of mistake in tavla markup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    /// Emphasis started with `_` or `__` that lasts until the end
+    /// of the input.
+    UnclosedEmphasis,
+    /// Emphasis that is closed right after it was started, like
+    /// `__` in `a __ b`.
+    EmptyEmphasis,
+    /// An underscore that starts emphasis where it was probably
+    /// meant literally, like in `file_ name`.
+    StrayUnderscore,
+    /// Emphasis closed while emphasis of the other level inside
+    /// of it is still open, like `_a __b_ c__`.
+    CrossedEmphasis,
+    /// A span like `{slow:` without its closing brace.
+    UnclosedSpan,
+    /// A span without any text, like `{slow: }`.
+    EmptySpan,
+    /// Something that looks like a span, but with an unknown
+    /// keyword, so it is spoken as text.
+    UnknownSpan,
+    /// Something that looks like an explicit pause, but without
+    /// a valid duration, so it is spoken as text.
+    InvalidPause,
+    /// Dots separated by whitespace, which are counted together
+    /// into one long pause, like `. .`.
+    SuspiciousPause,
+}
+
+impl Problem {
+    /// Problems that change how the markup is spoken are errors.
+    pub fn severity(self) -> Severity {
+        match self {
+            Problem::UnclosedEmphasis | Problem::UnclosedSpan | Problem::CrossedEmphasis => {
+                Severity::Error
+            }
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            Problem::UnclosedEmphasis => "emphasis is never closed and lasts until the end",
+            Problem::EmptyEmphasis => "emphasis without any text",
+            Problem::StrayUnderscore => {
+                "underscore starts emphasis, escape it as `\\_` to speak it"
+            }
+            Problem::CrossedEmphasis => "emphasis is closed before the emphasis inside of it",
+            Problem::UnclosedSpan => "span is never closed",
+            Problem::EmptySpan => "span without any text",
+            Problem::UnknownSpan => "unknown span keyword, this is spoken as text",
+            Problem::InvalidPause => {
+                "pause without a valid duration like `250ms` or `1.5s`, this is spoken as text"
+            }
+            Problem::SuspiciousPause => {
+                "dots separated by whitespace are counted into a single pause"
+            }
+        };
+        f.write_str(message)
+    }
+}
+
+/// A problem in markup, along with the byte range of the source
+/// where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub problem: Problem,
+    pub span: Range<usize>,
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        self.problem.severity()
+    }
+
+    /// The line and column of the start of the diagnostic in the
+    /// source, both counting from one. Columns count characters.
+    pub fn line_column(&self, source: &str) -> (usize, usize) {
+        let before = &source[..self.span.start];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        let column = before[line_start..].chars().count() + 1;
+        (line, column)
+    }
+
+    /// Formats the diagnostic like a compiler would, with the
+    /// name of the source, the line and a marker below the range.
+    pub fn report(&self, name: &str, source: &str) -> String {
+        let (line, column) = self.line_column(source);
+        let line_start = source[..self.span.start]
+            .rfind('\n')
+            .map_or(0, |idx| idx + 1);
+        let line_end = source[self.span.start..]
+            .find('\n')
+            .map_or(source.len(), |idx| self.span.start + idx);
+        let marked = source[self.span.start..self.span.end.min(line_end)]
+            .chars()
+            .count()
+            .max(1);
+
+        let gutter = " ".repeat(line.to_string().len());
+        let mut report = String::new();
+        let severity = match self.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        writeln!(report, "{}: {}", severity, self.problem).unwrap();
+        writeln!(report, "{}--> {}:{}:{}", gutter, name, line, column).unwrap();
+        writeln!(report, "{} |", gutter).unwrap();
+        writeln!(report, "{} | {}", line, &source[line_start..line_end]).unwrap();
+        writeln!(
+            report,
+            "{} | {}{}",
+            gutter,
+            " ".repeat(column - 1),
+            "^".repeat(marked)
+        )
+        .unwrap();
+        report
+    }
+}
+
+/// Checks tavla markup for likely mistakes, returning them in
+/// the order they appear in the source.
+///
+/// The markup can still be spoken when there are diagnostics,
+/// they only point at places where the result may surprise.
+///
+/// ```
+/// use tavla::{validate, Problem};
+///
+/// let diagnostics = validate("Press _one to continue");
+/// assert_eq!(diagnostics[0].problem, Problem::UnclosedEmphasis);
+/// assert_eq!(diagnostics[0].span, 6..7);
+/// ```
+pub fn validate(markup: &str) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        source: markup,
+        diagnostics: Vec::new(),
+        emphasis: Vec::new(),
+        spans: Vec::new(),
+    };
+    validator.validate();
+    validator
+        .diagnostics
+        .sort_by_key(|diagnostic| diagnostic.span.start);
+    validator.diagnostics
+}
+
+struct Validator<'a> {
+    source: &'a str,
+    diagnostics: Vec<Diagnostic>,
+    /// Emphasis that needs a closing marker, innermost last.
+    emphasis: Vec<OpenEmphasis>,
+    /// Spans that need a closing brace, innermost last.
+    spans: Vec<OpenSpan>,
+}
+
+struct OpenEmphasis {
+    marker: Range<usize>,
+    has_text: bool,
+    /// Stray underscores are reported on their own, not as
+    /// unclosed emphasis too.
+    is_stray: bool,
+}
+
+struct OpenSpan {
+    start: Range<usize>,
+    has_text: bool,
+}
+
+impl<'a> Validator<'a> {
+    /// Walks the source the way the tokenizer does, tracking the
+    /// emphasis and spans that are open.
+    fn validate(&mut self) {
+        let source = self.source;
+        let mut idx = 0;
+        let mut prev = None;
+
+        while let Some(c) = source[idx..].chars().next() {
+            let after = idx + c.len_utf8();
+            let next = source[after..].chars().next();
+            let len = match c {
+                '\\' if next.is_some_and(is_escapable) => {
+                    self.text();
+                    c.len_utf8() + next.map_or(0, char::len_utf8)
+                }
+                '_' if is_emphasis_marker(prev, next) => self.emphasis(idx, prev),
+                '.' if is_pause_dot(&source[..idx], prev, next) => self.pause(idx),
+                '{' => self.span_start(idx),
+                '}' if !self.spans.is_empty() => self.span_end(idx),
+                c => {
+                    if !c.is_whitespace() {
+                        self.text();
+                    }
+                    c.len_utf8()
+                }
+            };
+            idx += len;
+            prev = source[..idx].chars().next_back();
+        }
+
+        for emphasis in std::mem::take(&mut self.emphasis) {
+            if !emphasis.is_stray {
+                self.report(Problem::UnclosedEmphasis, emphasis.marker);
+            }
+        }
+        for span in std::mem::take(&mut self.spans) {
+            self.report(Problem::UnclosedSpan, span.start);
+        }
+    }
+
+    fn report(&mut self, problem: Problem, span: Range<usize>) {
+        self.diagnostics.push(Diagnostic { problem, span });
+    }
+
+    /// Records that there is text in the open emphasis and spans.
+    fn text(&mut self) {
+        for emphasis in &mut self.emphasis {
+            emphasis.has_text = true;
+        }
+        for span in &mut self.spans {
+            span.has_text = true;
+        }
+    }
+
+    fn emphasis(&mut self, idx: usize, prev: Option<char>) -> usize {
+        let len = if self.source[idx..].starts_with("__") {
+            2
+        } else {
+            1
+        };
+        let marker = idx..idx + len;
+
+        // Like the tokenizer, a marker closes the emphasis of its
+        // level if open, which should be the innermost one
+        let open = self
+            .emphasis
+            .iter()
+            .rposition(|open| open.marker.len() == len);
+        if let Some(open_idx) = open {
+            let is_innermost = open_idx + 1 == self.emphasis.len();
+            let open = self.emphasis.remove(open_idx);
+            if !is_innermost {
+                self.report(Problem::CrossedEmphasis, marker);
+            } else if !open.has_text {
+                self.report(Problem::EmptyEmphasis, open.marker.start..marker.end);
+            }
+            return len;
+        }
+
+        // Emphasis that starts right after a word or before
+        // whitespace was probably not meant as emphasis
+        let next = self.source[marker.end..].chars().next();
+        let is_stray =
+            prev.is_some_and(char::is_alphanumeric) || next.is_none_or(char::is_whitespace);
+        if is_stray {
+            self.report(Problem::StrayUnderscore, marker.clone());
+        }
+        self.emphasis.push(OpenEmphasis {
+            marker,
+            has_text: false,
+            is_stray,
+        });
+        len
+    }
+
+    /// Consumes a pause of dots and whitespace, like the tokenizer
+    /// does before text.
+    fn pause(&mut self, idx: usize) -> usize {
+        let rest = &self.source[idx..];
+        let run_len = rest
+            .find(|c: char| c != '.' && !c.is_whitespace())
+            .unwrap_or(rest.len());
+        let dots_len = rest[..run_len].rfind('.').map_or(0, |idx| idx + 1);
+        if rest[..dots_len].contains(char::is_whitespace) {
+            self.report(Problem::SuspiciousPause, idx..idx + dots_len);
+        }
+        run_len
+    }
+
+    fn span_start(&mut self, idx: usize) -> usize {
+        let rest = &self.source[idx..];
+        if let Some((_, len)) = explicit_pause(rest) {
+            return len;
+        }
+
+        match span_start(rest) {
+            Some((Span::Prosody(_), len)) | Some((Span::Reduced, len)) => {
+                self.spans.push(OpenSpan {
+                    start: idx..idx + len,
+                    has_text: false,
+                });
+                len
+            }
+            Some((Span::SayAs(_), len)) | Some((Span::Phoneme(_), len)) => {
+                let (content, literal_len, is_closed) = literal(&rest[len..]);
+                if !is_closed {
+                    self.report(Problem::UnclosedSpan, idx..idx + len);
+                } else if content.is_empty() {
+                    self.report(Problem::EmptySpan, idx..idx + len + literal_len);
+                }
+                self.text();
+                len + literal_len
+            }
+            None => {
+                if let Some(len) = pause_like(rest) {
+                    self.report(Problem::InvalidPause, idx..idx + len);
+                } else if let Some(len) = span_like(rest) {
+                    self.report(Problem::UnknownSpan, idx..idx + len);
+                }
+                self.text();
+                '{'.len_utf8()
+            }
+        }
+    }
+
+    fn span_end(&mut self, idx: usize) -> usize {
+        let span = self.spans.pop().expect("only called with open spans");
+        if !span.has_text {
+            self.report(Problem::EmptySpan, span.start.start..idx + 1);
+        }
+        '}'.len_utf8()
+    }
+}
+
+/// The length of something like `{pause 3 minutes}` that was
+/// not recognized as an explicit pause, up to the closing brace.
+fn pause_like(text: &str) -> Option<usize> {
+    let after_keyword = text.strip_prefix("{pause")?;
+    if !after_keyword.starts_with(|c: char| c.is_whitespace() || c == '}') {
+        return None;
+    }
+    let line = text.lines().next().unwrap_or(text);
+    Some(line.find('}').map_or(line.len(), |idx| idx + 1))
+}
+
+/// The length of something like `{slwo:`, a lowercase keyword
+/// between a brace and a colon.
+fn span_like(text: &str) -> Option<usize> {
+    let after_brace = text.strip_prefix('{')?;
+    let colon_idx = after_brace.find(':')?;
+    let keyword = &after_brace[..colon_idx];
+    let is_keyword = !keyword.is_empty() && keyword.chars().all(|c| c.is_ascii_lowercase());
+    is_keyword.then_some(1 + colon_idx + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn problems(markup: &str) -> Vec<(Problem, &str)> {
+        validate(markup)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.problem, &markup[diagnostic.span]))
+            .collect()
+    }
+
+    #[test]
+    fn valid_markup() {
+        for markup in &[
+            "rename snake_case_name to _camel_case_",
+            "_important_ and __critical__ {reduced: by the _way_}",
+            "Build failed on {slow: {high: _main_ branch}}. {fast: Details follow}",
+            "Wait{pause 250ms}for it.. Ticket {chars: INC-4711.b}, e.g. now...",
+            r"type \_\_init\_\_ and fn main() {}",
+        ] {
+            assert_eq!(
+                problems(markup),
+                vec![],
+                "Expected no problems in {}",
+                markup
+            );
+        }
+    }
+
+    #[test]
+    fn emphasis() {
+        assert_eq!(
+            problems("holodrio _there"),
+            vec![(Problem::UnclosedEmphasis, "_")]
+        );
+        assert_eq!(
+            problems("a ____ b __c__"),
+            vec![(Problem::EmptyEmphasis, "____")]
+        );
+        assert_eq!(
+            problems("open file_ now"),
+            vec![(Problem::StrayUnderscore, "_")]
+        );
+        assert_eq!(problems("_one __two__ three_ four"), vec![]);
+        assert_eq!(
+            problems("_one __two_ three__"),
+            vec![(Problem::CrossedEmphasis, "_")]
+        );
+    }
+
+    #[test]
+    fn nesting_agrees_with_tokenizer() {
+        use crate::token::{Token, Tokenizer};
+
+        for (markup, resumed) in &[
+            ("_one __two__ three_", Token::Emphasised("three".into())),
+            ("__one _two_ three__", Token::Strong("three".into())),
+        ] {
+            assert_eq!(
+                problems(markup),
+                vec![],
+                "Expected no problems in {}",
+                markup
+            );
+            let tokens: Vec<Token> = Tokenizer::new(markup).collect();
+            assert_eq!(tokens.len(), 3);
+            assert_eq!(&tokens[2], resumed);
+        }
+    }
+
+    #[test]
+    fn spans_and_pauses() {
+        assert_eq!(
+            problems("{chars: } {slwo: x} {pause 3 minutes}. . Done {tel: 555"),
+            vec![
+                (Problem::EmptySpan, "{chars: }"),
+                (Problem::UnknownSpan, "{slwo:"),
+                (Problem::InvalidPause, "{pause 3 minutes}"),
+                (Problem::SuspiciousPause, ". ."),
+                (Problem::UnclosedSpan, "{tel:"),
+            ]
+        );
+        assert_eq!(
+            problems("{slow: {loud: } x"),
+            vec![
+                (Problem::UnclosedSpan, "{slow:"),
+                (Problem::EmptySpan, "{loud: }"),
+            ]
+        );
+    }
+
+    #[test]
+    fn report() {
+        let source = "Welcome.\nPress _one to {slow: continue";
+        let reports: Vec<_> = validate(source)
+            .iter()
+            .map(|diagnostic| diagnostic.report("prompt.txt", source))
+            .collect();
+        assert_eq!(
+            reports,
+            vec![
+                "error: emphasis is never closed and lasts until the end
+ --> prompt.txt:2:7
+  |
+2 | Press _one to {slow: continue
+  |       ^
+",
+                "error: span is never closed
+ --> prompt.txt:2:15
+  |
+2 | Press _one to {slow: continue
+  |               ^^^^^^
+"
+            ]
+        );
+    }
+}
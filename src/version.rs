@@ -1,16 +1,175 @@
 pub use err::Error;
+use std::cmp::Ordering;
+use std::fmt;
 use std::process::{Command, Output};
 
-/// Tries to call the given command with --version and
-/// captures its output and status.
+/// The version of a speech synthesizer, as reported by the
+/// program, like `1.48.15` or `1.52-dev`.
 ///
-/// Returns the output if version successfully detected.
+/// Versions compare by their numbers, with a pre-release like
+/// `-dev` ordering before the release itself. Build metadata
+/// after a plus sign is ignored:
 ///
-/// Returns error in case of unsuccessful exit.
-pub fn detect_version(cmd: &str) -> Result<Output, Error> {
-    detect_version_with_arg(cmd, Some("--version"))
+/// ```
+/// # use tavla::BackendVersion;
+/// let version = BackendVersion::parse("eSpeak NG text-to-speech: 1.52-dev  Data at: /usr").unwrap();
+/// assert_eq!(version.pre(), Some("dev"));
+/// assert_eq!(version.to_string(), "1.52-dev");
+/// assert!(version > BackendVersion::new(1, 49, 0));
+/// assert!(version < BackendVersion::new(1, 52, 0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BackendVersion {
+    major: u32,
+    minor: u32,
+    patch: u32,
+    /// Whether the patch number was given, to print versions
+    /// like they were parsed.
+    has_patch: bool,
+    // Pre-release and build metadata as written, like `-dev+1a2b`,
+    // boxed together so that errors holding versions stay small
+    suffix: Option<Box<str>>,
 }
 
+impl BackendVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> BackendVersion {
+        BackendVersion {
+            major,
+            minor,
+            patch,
+            has_patch: true,
+            suffix: None,
+        }
+    }
+
+    /// Finds the first version in the output of a program, which is
+    /// a word like `5.812` or `1.50.1-dev` that starts with numbers
+    /// separated by dots. A pre-release may follow after a dash and
+    /// build metadata after a plus sign.
+    pub fn parse(output: &str) -> Option<BackendVersion> {
+        output
+            .split_whitespace()
+            .map(|word| word.trim_end_matches([',', ';', ')']))
+            .find_map(BackendVersion::parse_word)
+    }
+
+    fn parse_word(word: &str) -> Option<BackendVersion> {
+        let (numbers, suffix) = match word.find(['-', '+']) {
+            Some(idx) => (&word[..idx], Some(&word[idx..])),
+            None => (word, None),
+        };
+        let numbers = numbers
+            .split('.')
+            .map(|number| number.parse().ok())
+            .collect::<Option<Vec<u32>>>()?;
+        if numbers.len() < 2 || numbers.len() > 3 {
+            return None;
+        }
+
+        Some(BackendVersion {
+            major: numbers[0],
+            minor: numbers[1],
+            patch: numbers.get(2).copied().unwrap_or(0),
+            has_patch: numbers.len() == 3,
+            suffix: suffix.filter(|suffix| suffix.len() > 1).map(Box::from),
+        })
+    }
+
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    pub fn patch(&self) -> u32 {
+        self.patch
+    }
+
+    /// The pre-release after a dash, like `dev`.
+    pub fn pre(&self) -> Option<&str> {
+        let suffix = self.suffix.as_deref()?.strip_prefix('-')?;
+        let pre = suffix.split('+').next().unwrap_or(suffix);
+        Some(pre).filter(|pre| !pre.is_empty())
+    }
+
+    /// Build metadata after a plus sign, like `1a2b`.
+    pub fn build(&self) -> Option<&str> {
+        let suffix = self.suffix.as_deref()?;
+        Some(&suffix[suffix.find('+')? + 1..]).filter(|build| !build.is_empty())
+    }
+
+    fn numbers(&self) -> (u32, u32, u32) {
+        (self.major, self.minor, self.patch)
+    }
+}
+
+impl PartialEq for BackendVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for BackendVersion {}
+
+impl PartialOrd for BackendVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BackendVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let numbers = self.numbers().cmp(&other.numbers());
+        // Pre-releases come before the release
+        let pre = match (self.pre(), other.pre()) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(pre), Some(other_pre)) => compare_pre(pre, other_pre),
+        };
+        numbers.then(pre)
+    }
+}
+
+/// Compares pre-releases like `rc.9` and `rc.10` by their dot
+/// separated parts, numerically where both are numbers, with
+/// numbers before words, like semantic versioning does.
+fn compare_pre(pre: &str, other: &str) -> Ordering {
+    let mut parts = pre.split('.');
+    let mut other_parts = other.split('.');
+    loop {
+        let ordering = match (parts.next(), other_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(part), Some(other_part)) => {
+                match (part.parse::<u64>(), other_part.parse::<u64>()) {
+                    (Ok(number), Ok(other_number)) => number.cmp(&other_number),
+                    (Ok(_), Err(_)) => Ordering::Less,
+                    (Err(_), Ok(_)) => Ordering::Greater,
+                    (Err(_), Err(_)) => part.cmp(other_part),
+                }
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+impl fmt::Display for BackendVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if self.has_patch {
+            write!(f, ".{}", self.patch)?;
+        }
+        write!(f, "{}", self.suffix.as_deref().unwrap_or(""))
+    }
+}
+
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
 pub fn detect_version_with_arg(program: &str, arg: Option<&str>) -> Result<Output, Error> {
     let mut cmd = Command::new(program);
 
@@ -18,18 +177,43 @@ pub fn detect_version_with_arg(program: &str, arg: Option<&str>) -> Result<Outpu
         cmd.arg(arg);
     }
 
+    detect_version_of(&mut cmd)
+}
+
+/// Runs the given command, already set up with the arguments
+/// that make it report its version, and captures its output.
+pub fn detect_version_of(cmd: &mut Command) -> Result<Output, Error> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
     cmd.output()
-        .or_else(|cause| Err(Error::version_detect_io(program, cause)))
+        .map_err(|cause| Error::version_detect_io(&program, cause))
         .and_then(|output| {
             if output.status.success() {
                 Ok(output)
             } else {
-                Err(Error::unsuccessful_exit(program, output))
+                Err(Error::unsuccessful_exit(&program, output))
             }
         })
 }
 
+/// Parses the version from the output of a command and checks
+/// that it is at least the given minimum.
+///
+/// Not every program reports a version, so output without one is
+/// accepted, returning `None`.
+pub fn require_version(
+    output: &Output,
+    minimum: &BackendVersion,
+) -> Result<Option<BackendVersion>, Error> {
+    let version = BackendVersion::parse(&String::from_utf8_lossy(&output.stdout))
+        .or_else(|| BackendVersion::parse(&String::from_utf8_lossy(&output.stderr)));
+    match version {
+        Some(version) if version < *minimum => Err(Error::too_old(version, minimum)),
+        version => Ok(version),
+    }
+}
+
 mod err {
+    use super::BackendVersion;
     use failure::{Backtrace, Fail};
     use std::io;
     use std::process::Output;
@@ -52,6 +236,15 @@ mod err {
             cause: io::Error,
             backtrace: Backtrace,
         },
+        #[fail(
+            display = "version {} is too old, at least version {} is required.",
+            version, minimum
+        )]
+        TooOld {
+            version: BackendVersion,
+            minimum: BackendVersion,
+            backtrace: Backtrace,
+        },
     }
 
     impl Error {
@@ -70,6 +263,14 @@ mod err {
                 backtrace: Backtrace::new(),
             }
         }
+
+        pub fn too_old(version: BackendVersion, minimum: &BackendVersion) -> Error {
+            Error::TooOld {
+                version,
+                minimum: minimum.clone(),
+                backtrace: Backtrace::new(),
+            }
+        }
     }
 }
 
@@ -79,12 +280,13 @@ mod test {
 
     #[test]
     fn detect_cargo_version() {
-        detect_version("cargo").expect("cargo could not be detected with detect_version()");
+        detect_version_of(Command::new("cargo").arg("--version"))
+            .expect("cargo could not be detected with detect_version_of()");
     }
 
     #[test]
     fn detect_nonsense_version() {
-        let nonsense_version_err = detect_version("wrdlbrnft_42")
+        let nonsense_version_err = detect_version_of(Command::new("wrdlbrnft_42").arg("--version"))
             .expect_err("expected wrdlbrnft_42 to be something nonsensable and err");
 
         match nonsense_version_err {
@@ -99,7 +301,7 @@ mod test {
     #[cfg(unix)]
     #[test]
     fn detect_unsupported_cmd() {
-        let false_version_err = detect_version("false")
+        let false_version_err = detect_version_of(Command::new("false").arg("--version"))
             .expect_err("Expected false to exit unsuccessfully on any unix, making it unsupported by our method.");
 
         match false_version_err {
@@ -110,4 +312,72 @@ mod test {
             ),
         }
     }
+
+    #[test]
+    fn parse_versions() {
+        let version = |output| BackendVersion::parse(output).map(|v| v.to_string());
+        assert_eq!(
+            version("eSpeak text-to-speech: 1.48.15  16.Apr.15  Data at: /usr/share/espeak-data"),
+            Some("1.48.15".to_string())
+        );
+        assert_eq!(
+            version("eSpeak NG text-to-speech: 1.50.1-dev  Data at: /usr/share/espeak-ng-data"),
+            Some("1.50.1-dev".to_string())
+        );
+        assert_eq!(
+            version("Microsoft (R) Windows Script Host Version 5.812"),
+            Some("5.812".to_string())
+        );
+        let rc = BackendVersion::parse("tool 2.1.0-rc.1+build.7").unwrap();
+        assert_eq!(rc.pre(), Some("rc.1"));
+        assert_eq!(rc.build(), Some("build.7"));
+        assert_eq!(rc.to_string(), "2.1.0-rc.1+build.7");
+        let build = BackendVersion::parse("tool 2.1+build.7").unwrap();
+        assert_eq!((build.pre(), build.build()), (None, Some("build.7")));
+        assert_eq!(version("Usage: say [-v voice]"), None);
+        assert_eq!(version("Released 2015, 1.2.3.4"), None);
+    }
+
+    #[test]
+    fn compare_versions() {
+        let dev = BackendVersion::parse("1.52-dev").unwrap();
+        assert!(dev < BackendVersion::new(1, 52, 0));
+        assert!(dev > BackendVersion::new(1, 51, 9));
+        let rc = |version| BackendVersion::parse(version).unwrap();
+        assert!(rc("1.52-rc.9") < rc("1.52-rc.10"));
+        assert!(rc("1.52-rc") < rc("1.52-rc.1"));
+        assert!(rc("1.52-alpha.2") < rc("1.52-beta"));
+        assert!(rc("1.52-1") < rc("1.52-dev"));
+        assert!(BackendVersion::new(1, 48, 15) < BackendVersion::new(1, 49, 0));
+        assert!(BackendVersion::new(2, 0, 0) > BackendVersion::new(1, 99, 99));
+        assert_eq!(
+            BackendVersion::parse("1.52+abc").unwrap(),
+            BackendVersion::new(1, 52, 0)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn require_minimum_version() {
+        let output = Command::new("echo").arg("tool 1.4.2").output().unwrap();
+        let version = require_version(&output, &BackendVersion::new(1, 4, 0)).unwrap();
+        assert_eq!(version, Some(BackendVersion::new(1, 4, 2)));
+
+        match require_version(&output, &BackendVersion::new(1, 10, 0)) {
+            Err(Error::TooOld { version, .. }) => assert_eq!(version.minor(), 4),
+            other => panic!("Expected TooOld error, got: {:?}", other),
+        }
+
+        let output = Command::new("echo").arg("tool 1.52-dev").output().unwrap();
+        match require_version(&output, &BackendVersion::new(1, 52, 0)) {
+            Err(Error::TooOld { version, .. }) => assert_eq!(version.to_string(), "1.52-dev"),
+            other => panic!("Expected TooOld error, got: {:?}", other),
+        }
+
+        let output = Command::new("echo").arg("no version").output().unwrap();
+        assert_eq!(
+            require_version(&output, &BackendVersion::new(1, 0, 0)).unwrap(),
+            None
+        );
+    }
 }
@@ -1,18 +1,48 @@
+use crate::chunked::ChunkedSpeech;
+use crate::settings::Settings;
 use crate::speech::Speech;
+use crate::ssml::Error as SsmlError;
+use crate::utterance::Utterance;
 use failure::Fail;
 use std::fmt::{Debug, Display};
 use std::path::Path;
 
 /// A trait for things that can speak.
+///
+/// Voices speak [`Utterance`](struct.Utterance.html)s. All other
+/// ways of speaking, like marked up strings or SSML documents,
+/// turn their input into an utterance first.
 pub trait Voice {
     type Speech: Speech;
-    type Error: Fail + Send + Sync + Debug + Display;
+    type Error: Fail + Send + Sync + Debug + Display + From<SsmlError>;
+
+    /// The settings used when speaking.
+    fn settings(&self) -> &Settings;
+
+    /// The settings used when speaking, for modification.
+    fn settings_mut(&mut self) -> &mut Settings;
+
+    /// Speaks the given utterance out loud.
+    fn speak_utterance(&self, utterance: &Utterance) -> Result<Self::Speech, Self::Error>;
+
+    /// Speaks the given utterance to an uncompressed audio
+    /// file in WAV format at the given path.
+    fn speak_utterance_to_file<P>(
+        &self,
+        utterance: &Utterance,
+        wav_file_path: P,
+    ) -> Result<Self::Speech, Self::Error>
+    where
+        P: AsRef<Path>;
 
     /// Speaks the given sentence out loud.
     /// Emphasized words can be wrapped in underscores.
     fn speak<S>(&self, sentence: S) -> Result<Self::Speech, Self::Error>
     where
-        S: AsRef<str>;
+        S: AsRef<str>,
+    {
+        self.speak_utterance(&Utterance::parse(sentence.as_ref()))
+    }
 
     /// Speaks the given sentence to an uncompressed audio
     /// file in WAV format at the given path.
@@ -23,5 +53,75 @@ pub trait Voice {
     ) -> Result<Self::Speech, Self::Error>
     where
         S: AsRef<str>,
-        P: AsRef<Path>;
+        P: AsRef<Path>,
+    {
+        self.speak_utterance_to_file(&Utterance::parse(sentence.as_ref()), wav_file_path)
+    }
+
+    /// Speaks the given markup or utterance one sentence or
+    /// paragraph at a time, each in a separate speech.
+    ///
+    /// The returned speech tells which segment is playing, and
+    /// can skip segments or cancel at a segment boundary.
+    fn speak_chunked<U>(&self, utterance: U) -> Result<ChunkedSpeech<Self::Speech>, Self::Error>
+    where
+        U: Into<Utterance>,
+        Self: Clone + Send + 'static,
+        Self::Speech: Send + 'static,
+    {
+        ChunkedSpeech::start(self.clone(), &utterance.into())
+    }
+
+    /// Speaks the given SSML document out loud.
+    ///
+    /// The document is validated first. Backends without SSML
+    /// support speak a translation into their own markup, where
+    /// unsupported elements are spoken as plain text.
+    fn speak_ssml<S>(&self, ssml: S) -> Result<Self::Speech, Self::Error>
+    where
+        S: AsRef<str>,
+    {
+        self.speak_utterance(&Utterance::from_ssml(ssml.as_ref())?)
+    }
+
+    /// Speaks the given SSML document to an uncompressed audio
+    /// file in WAV format at the given path.
+    fn speak_ssml_to_file<S, P>(
+        &self,
+        ssml: S,
+        wav_file_path: P,
+    ) -> Result<Self::Speech, Self::Error>
+    where
+        S: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        self.speak_utterance_to_file(&Utterance::from_ssml(ssml.as_ref())?, wav_file_path)
+    }
+
+    /// Speaks the given Markdown document out loud.
+    ///
+    /// Headings, paragraphs, list items and table cells are
+    /// separated by pauses and emphasis is preserved. Link targets
+    /// and code blocks are not spoken, images only by their alt
+    /// text.
+    fn speak_markdown<S>(&self, markdown: S) -> Result<Self::Speech, Self::Error>
+    where
+        S: AsRef<str>,
+    {
+        self.speak_utterance(&Utterance::from_markdown(markdown.as_ref()))
+    }
+
+    /// Speaks the given Markdown document to an uncompressed
+    /// audio file in WAV format at the given path.
+    fn speak_markdown_to_file<S, P>(
+        &self,
+        markdown: S,
+        wav_file_path: P,
+    ) -> Result<Self::Speech, Self::Error>
+    where
+        S: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        self.speak_utterance_to_file(&Utterance::from_markdown(markdown.as_ref()), wav_file_path)
+    }
 }
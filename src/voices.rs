@@ -0,0 +1,299 @@
+//! Information on the voices that backends offer, parsed from
+//! the listings of `espeak --voices`, `say -v ?` and SAPI.
+
+/// The speech synthesizer that offers a voice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// `espeak`, on any system where it is installed.
+    Espeak,
+    /// `say` on Mac systems.
+    Say,
+    /// SAPI speech synthesis on Windows systems.
+    Sapi,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Gender {
+    Female,
+    Male,
+}
+
+/// The age a voice sounds like, as far as the backend tells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Age {
+    Child,
+    Teen,
+    Adult,
+    Senior,
+}
+
+impl Age {
+    fn from_years(years: u32) -> Age {
+        match years {
+            0..=12 => Age::Child,
+            13..=19 => Age::Teen,
+            20..=59 => Age::Adult,
+            _ => Age::Senior,
+        }
+    }
+}
+
+/// A voice of a backend, e.g. to show in a voice picker.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VoiceInfo {
+    /// What selects the voice in the backend, like a voice file
+    /// of `espeak` or the name of a voice of `say`.
+    pub id: String,
+    /// The name of the voice for display.
+    pub name: String,
+    /// Tags of the languages the voice speaks, like `en-US` or
+    /// `de`, the main language first.
+    pub languages: Vec<String>,
+    pub gender: Option<Gender>,
+    pub age: Option<Age>,
+    pub backend: Backend,
+}
+
+impl VoiceInfo {
+    /// Checks if the voice speaks the language with the given tag,
+    /// ignoring case. Tags without a region like `en` match voices
+    /// for any region, like `en-GB`.
+    pub fn speaks(&self, tag: &str) -> bool {
+        let tag = language_tag(tag).to_lowercase();
+        self.languages.iter().any(|language| {
+            let language = language.to_lowercase();
+            language == tag
+                || (language.starts_with(&tag) && language[tag.len()..].starts_with('-'))
+        })
+    }
+}
+
+/// Normalizes a language tag or locale like `en_us` into the
+/// usual form of language tags, like `en-US`.
+pub(crate) fn language_tag(tag: &str) -> String {
+    tag.split(['-', '_'])
+        .enumerate()
+        .map(|(idx, subtag)| {
+            if idx > 0 && subtag.len() == 2 {
+                subtag.to_uppercase()
+            } else {
+                subtag.to_lowercase()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Parses the output of `espeak --voices`, a table with the
+/// columns `Pty Language Age/Gender VoiceName File Other Languages`.
+///
+/// Other languages are listed with priorities, like `(en-r 5)(en 2)`.
+pub(crate) fn parse_espeak_voices(listing: &str) -> Vec<VoiceInfo> {
+    listing
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            if columns.len() < 5 {
+                return None;
+            }
+
+            let others = columns[5..].join(" ");
+            let languages = std::iter::once(columns[1])
+                .chain(
+                    others
+                        .split('(')
+                        .filter_map(|other| other.split_whitespace().next()),
+                )
+                .map(language_tag)
+                .collect();
+            // Like M or F in espeak, and --/M or 70/F in espeak-ng
+            let (age, gender) = columns[2].rsplit_once('/').unwrap_or(("", columns[2]));
+            let gender = match gender {
+                "M" => Some(Gender::Male),
+                "F" => Some(Gender::Female),
+                _ => None,
+            };
+            let age = age.parse().ok().map(Age::from_years);
+
+            Some(VoiceInfo {
+                id: columns[4].to_string(),
+                name: columns[3].to_string(),
+                languages,
+                gender,
+                age,
+                backend: Backend::Espeak,
+            })
+        })
+        .collect()
+}
+
+/// Parses the output of `say -v ?`, with the name, the locale
+/// and a sample sentence after a hash on every line.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub(crate) fn parse_say_voices(listing: &str) -> Vec<VoiceInfo> {
+    listing
+        .lines()
+        .filter_map(|line| {
+            let description = line.split('#').next()?.trim_end();
+            let (name, locale) = description.rsplit_once(char::is_whitespace)?;
+            let name = name.trim();
+            if name.is_empty() {
+                return None;
+            }
+
+            Some(VoiceInfo {
+                id: name.to_string(),
+                name: name.to_string(),
+                languages: vec![language_tag(locale)],
+                gender: None,
+                age: None,
+                backend: Backend::Say,
+            })
+        })
+        .collect()
+}
+
+/// Parses the voices listed by the VB script, one per line with
+/// the name, the hexadecimal language IDs separated by semicolons,
+/// the gender and the age, separated by tabs.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn parse_sapi_voices(listing: &str) -> Vec<VoiceInfo> {
+    listing
+        .lines()
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.trim_end_matches('\r').split('\t').collect();
+            if columns.len() != 4 || columns[0].is_empty() {
+                return None;
+            }
+
+            let languages = columns[1]
+                .split(';')
+                .filter_map(|lcid| u32::from_str_radix(lcid.trim(), 16).ok())
+                .filter_map(lcid_tag)
+                .map(String::from)
+                .collect();
+            let gender = match columns[2] {
+                "Female" => Some(Gender::Female),
+                "Male" => Some(Gender::Male),
+                _ => None,
+            };
+            let age = match columns[3] {
+                "Child" => Some(Age::Child),
+                "Teen" => Some(Age::Teen),
+                "Adult" => Some(Age::Adult),
+                "Senior" => Some(Age::Senior),
+                _ => None,
+            };
+
+            Some(VoiceInfo {
+                id: columns[0].to_string(),
+                name: columns[0].to_string(),
+                languages,
+                gender,
+                age,
+                backend: Backend::Sapi,
+            })
+        })
+        .collect()
+}
+
+/// The language tags of the Windows language IDs that SAPI voices
+/// are commonly available for.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn lcid_tag(lcid: u32) -> Option<&'static str> {
+    let tag = match lcid {
+        0x0401 => "ar-SA",
+        0x0404 => "zh-TW",
+        0x0405 => "cs-CZ",
+        0x0406 => "da-DK",
+        0x0407 => "de-DE",
+        0x0408 => "el-GR",
+        0x0409 => "en-US",
+        0x040A | 0x0C0A => "es-ES",
+        0x040B => "fi-FI",
+        0x040C => "fr-FR",
+        0x040D => "he-IL",
+        0x040E => "hu-HU",
+        0x0410 => "it-IT",
+        0x0411 => "ja-JP",
+        0x0412 => "ko-KR",
+        0x0413 => "nl-NL",
+        0x0414 => "nb-NO",
+        0x0415 => "pl-PL",
+        0x0416 => "pt-BR",
+        0x0419 => "ru-RU",
+        0x041D => "sv-SE",
+        0x041E => "th-TH",
+        0x041F => "tr-TR",
+        0x0804 => "zh-CN",
+        0x0807 => "de-CH",
+        0x0809 => "en-GB",
+        0x080A => "es-MX",
+        0x0816 => "pt-PT",
+        0x0C07 => "de-AT",
+        0x0C09 => "en-AU",
+        0x0C0C => "fr-CA",
+        0x1009 => "en-CA",
+        0x4009 => "en-IN",
+        _ => return None,
+    };
+    Some(tag)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn espeak_voices() {
+        let voices = parse_espeak_voices(
+            "Pty Language Age/Gender VoiceName          File          Other Languages
+ 5  af             M  afrikaans            other/af
+ 5  en-gb          M  english              default       (en 2)
+ 5  de             70/F  German_(Austria)  gmw/de
+",
+        );
+        assert_eq!(voices.len(), 3);
+        assert_eq!(voices[1].id, "default");
+        assert_eq!(voices[1].languages, vec!["en-GB", "en"]);
+        assert_eq!(voices[1].gender, Some(Gender::Male));
+        assert_eq!(voices[2].name, "German_(Austria)");
+        assert_eq!(
+            (voices[2].gender, voices[2].age),
+            (Some(Gender::Female), Some(Age::Senior))
+        );
+    }
+
+    #[test]
+    fn say_and_sapi_voices() {
+        let say = parse_say_voices(
+            "Alex                en_US    # Most people recognize me by my voice.
+Bad News            en_US    # The light you see at the end of the tunnel is a train.
+Eddy (English (UK)) en_GB    # Hello! My name is Eddy.
+",
+        );
+        let names: Vec<_> = say.iter().map(|voice| voice.name.as_str()).collect();
+        assert_eq!(names, vec!["Alex", "Bad News", "Eddy (English (UK))"]);
+        assert_eq!(say[2].languages, vec!["en-GB"]);
+
+        let sapi = parse_sapi_voices(
+            "Microsoft Script Host\r\nMicrosoft Zira Desktop\t409\tFemale\tAdult\r\nMicrosoft Hedda Desktop\t407;9\tFemale\tAdult\r\n",
+        );
+        assert_eq!(sapi.len(), 2);
+        assert_eq!(sapi[0].languages, vec!["en-US"]);
+        assert_eq!(sapi[1].languages, vec!["de-DE"]);
+        assert_eq!(sapi[1].age, Some(Age::Adult));
+    }
+
+    #[test]
+    fn speaks_language() {
+        let voice = &parse_say_voices("Anna de_DE # Hallo, ich heiße Anna.")[0];
+        assert!(voice.speaks("de"));
+        assert!(voice.speaks("de-de"));
+        assert!(voice.speaks("de_DE"));
+        assert!(!voice.speaks("de-AT"));
+        assert!(!voice.speaks("d"));
+        assert!(!voice.speaks("en"));
+    }
+}
@@ -1,6 +1,9 @@
 use std::env::temp_dir;
 use std::fs::{remove_file, File};
-use tavla::{espeak, Speech, Voice};
+use tavla::{
+    espeak, AnyVoice, Backend, EspeakError, EspeakFlavor, PhoneticAlphabet, Speech, Transcriber,
+    Utterance, Voice,
+};
 
 /// If espeak is unavailable, it should not be obtainable (Err).
 /// If available, it must be callable.
@@ -71,3 +74,167 @@ fn speak_to_file_espeak() {
         }
     }
 }
+
+#[test]
+fn speak_ssml_espeak() {
+    match espeak() {
+        Err(err) => {
+            // not being available is an ok outcome, test successful
+            println!("espeak not available: {:?}.", err);
+        }
+        // If it is, valid SSML must be spoken and invalid SSML rejected
+        Ok(espeak) => {
+            espeak
+                .speak_ssml("<speak>Hello <emphasis>SSML</emphasis></speak>")
+                .expect("espeak obtained, but failed to speak SSML")
+                .await_done()
+                .expect("espeak obtained, but failed to speak SSML until done");
+
+            espeak
+                .speak_ssml("<speak>Hello <emphasis>SSML</speak>")
+                .err()
+                .expect("Expected malformed SSML to be rejected");
+        }
+    }
+}
+
+#[test]
+fn speak_phonemes_espeak() {
+    match espeak() {
+        Err(err) => {
+            // not being available is an ok outcome, test successful
+            println!("espeak not available: {:?}.", err);
+        }
+        // If it is, phonemes must be validated and spoken
+        Ok(espeak) => {
+            espeak
+                .validate_phonemes(PhoneticAlphabet::Ipa, "ˈtɑːvlə")
+                .expect("Expected valid IPA to be accepted");
+            espeak
+                .validate_phonemes(PhoneticAlphabet::Sapi, "t aa1 v l ax")
+                .expect_err("Expected SAPI phonemes to be rejected");
+
+            espeak
+                .speak("Hello from {espeak:t'A:vl@|tavla}.")
+                .expect("espeak obtained, but failed to speak phonemes")
+                .await_done()
+                .expect("espeak obtained, but failed to speak phonemes until done");
+        }
+    }
+}
+
+#[test]
+fn speak_chunked_espeak() {
+    match espeak() {
+        Err(err) => {
+            println!("espeak not available: {:?}.", err);
+        }
+        // Every sentence is a segment that can be skipped
+        Ok(espeak) => {
+            let mut speech = espeak
+                .speak_chunked("First sentence. Second sentence. Third sentence.")
+                .expect("espeak obtained, but failed to speak in segments");
+
+            assert_eq!(speech.segments().len(), 3);
+            assert_eq!(speech.current_segment(), Some(0));
+            speech.skip().unwrap();
+            speech.cancel().unwrap();
+            speech.await_done().unwrap();
+            assert_eq!(speech.current_segment(), None);
+        }
+    }
+}
+
+#[test]
+fn select_espeak_voice() {
+    match espeak() {
+        Err(err) => {
+            println!("espeak not available: {:?}.", err);
+        }
+        // Known voices are passed on, unknown ones are errors
+        Ok(espeak) => {
+            let german = espeak
+                .clone()
+                .with_voice("de", Some("f3"))
+                .expect("espeak obtained, but has no german voice");
+            assert_eq!(german.voice(), Some("de+f3"));
+            german
+                .speak("Hallo von espeak.")
+                .expect("espeak obtained, but failed to speak with a voice")
+                .await_done()
+                .unwrap();
+
+            match espeak.with_voice("klingon+whisper", None) {
+                Err(EspeakError::UnknownVoice { voice, .. }) => assert_eq!(voice, "klingon"),
+                other => panic!("Expected unknown voice, got: {:?}", other),
+            }
+        }
+    }
+}
+
+#[test]
+fn list_espeak_voices() {
+    match espeak() {
+        Err(err) => {
+            println!("espeak not available: {:?}.", err);
+        }
+        // Every listed voice must be selectable
+        Ok(espeak) => {
+            let voices = espeak
+                .voices()
+                .expect("espeak obtained, but cannot list voices");
+            let german = voices
+                .iter()
+                .find(|voice| voice.speaks("de"))
+                .expect("espeak obtained, but has no german voice");
+            assert_eq!(german.backend, Backend::Espeak);
+            espeak
+                .with_voice(&german.id, None)
+                .expect("listed voice cannot be selected");
+
+            assert!(AnyVoice::voices().iter().any(|voice| voice.speaks("de")));
+            AnyVoice::for_language("de").expect("no voice for a listed language");
+        }
+    }
+}
+
+#[test]
+fn detect_espeak_flavor() {
+    match espeak() {
+        Err(err) => {
+            println!("espeak not available: {:?}.", err);
+        }
+        // Only espeak-ng reads phonemes in SSML
+        Ok(espeak) => {
+            assert_eq!(
+                espeak.features().ssml_phonemes,
+                espeak.flavor() == EspeakFlavor::EspeakNg
+            );
+        }
+    }
+}
+
+#[test]
+fn transcribe_with_espeak() {
+    match espeak() {
+        Err(err) => {
+            println!("espeak not available: {:?}.", err);
+        }
+        // Transcriptions must not be empty and cover every word
+        Ok(espeak) => {
+            let espeak = espeak
+                .with_voice("en", None)
+                .expect("espeak obtained, but has no english voice");
+            let phonemes = espeak
+                .transcribe("Hello", PhoneticAlphabet::Espeak)
+                .expect("espeak obtained, but failed to transcribe");
+            assert!(!phonemes.is_empty());
+
+            let words = espeak
+                .transcribe_words(&Utterance::parse("Hello, world."), PhoneticAlphabet::Espeak)
+                .expect("espeak obtained, but failed to transcribe words");
+            assert_eq!(words.len(), 2);
+            assert_eq!(words[0].phonemes, phonemes);
+        }
+    }
+}